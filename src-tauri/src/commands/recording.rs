@@ -1,7 +1,17 @@
-use crate::error::AppError;
-use crate::services::{PlayerService, RecorderService};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    DryRunReport, HotkeyAction, PlaybackOptions, PlaybackStatus, RecordingOptions,
+    RecordingStatus, ScreenGeometry, SessionStatus,
+};
+use crate::services::{HotkeyService, PlayerService, RdevEventSource, RecorderService};
 use crate::state::AppState;
-use tauri::State;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{Emitter, Manager, State};
+
+/// How often a recording's auto-stop limits are checked.
+const LIMIT_POLL: Duration = Duration::from_millis(250);
 
 #[tauri::command]
 pub async fn start_recording(
@@ -9,25 +19,104 @@ pub async fn start_recording(
     app_handle: tauri::AppHandle,
     session_name: String,
     description: Option<String>,
+    options: Option<RecordingOptions>,
 ) -> Result<i64, String> {
+    let options = options.unwrap_or_default();
+    *state.recording_options.lock().unwrap() = options.clone();
+    begin_recording(
+        &state,
+        app_handle,
+        &session_name,
+        description.as_deref(),
+        options,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn stop_recording(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    finish_recording(&state, &app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_privacy_mode(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+    enabled: bool,
+) -> Result<(), String> {
+    set_privacy(&state, &app_handle, enabled)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn pause_recording(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    set_recording_paused(&state, &app_handle, true).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn resume_recording(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    set_recording_paused(&state, &app_handle, false).map_err(|e| e.to_string())
+}
+
+/// Create a session and start recording into it. Shared by the command and the hotkey.
+pub async fn begin_recording(
+    state: &AppState,
+    app_handle: tauri::AppHandle,
+    session_name: &str,
+    description: Option<&str>,
+    options: RecordingOptions,
+) -> AppResult<i64> {
     // Check and set recording flag briefly, release guard before any await
     {
         let mut is_recording = state.is_recording.lock().unwrap();
         if *is_recording {
-            return Err(AppError::AlreadyRecording.to_string());
+            return Err(AppError::AlreadyRecording);
         }
         *is_recording = true;
     }
 
+    // 倒计时期间不录制任何输入
+    if options.countdown_secs > 0 {
+        count_down(state, &app_handle, options.countdown_secs).await?;
+    }
+    let limited = options.has_limits();
+
     // 创建新会话 (await while not holding std mutex guards)
-    let session_id = {
+    let created = {
         let repository = state.repository.lock().await;
         repository
-            .create_session(&session_name, description.as_deref())
+            .create_session(session_name, description, SessionStatus::Recording)
             .await
-            .map_err(|e| e.to_string())?
+    };
+    let session_id = match created {
+        Ok(id) => id,
+        Err(e) => {
+            *state.is_recording.lock().unwrap() = false;
+            return Err(e);
+        }
     };
 
+    // 记录屏幕信息，回放时用于坐标换算
+    if let Some(screen) = ScreenGeometry::detect(Some(&app_handle)) {
+        let repository = state.repository.lock().await;
+        if let Err(e) = repository.set_session_screen(session_id, &screen).await {
+            eprintln!("Failed to store screen geometry: {}", e);
+        }
+    }
+
     // 设置当前会话ID
     {
         let mut current_session = state.current_session_id.lock().unwrap();
@@ -35,92 +124,359 @@ pub async fn start_recording(
     }
 
     // 启动录制
-    let is_recording_clone = state.is_recording.clone();
-    // 克隆 repository Arc<TokioMutex<..>> to pass into thread
-    let repository_for_thread = state.repository.clone();
-    let app_handle_clone = app_handle.clone();
-
-    // 创建并保存 in_flight 计数器到 state，以便 stop_recording 能等待
-    let in_flight_counter = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    {
-        let mut slot = state.recorder_in_flight.lock().await;
-        *slot = Some(in_flight_counter.clone());
-    }
-
-    std::thread::spawn(move || {
-        if let Err(e) = RecorderService::start_recording(
-            is_recording_clone,
-            app_handle_clone,
-            repository_for_thread,
-            session_id,
-            Some(in_flight_counter),
-        ) {
-            eprintln!("Recording error: {:?}", e);
+    let hotkeys = state
+        .config
+        .hotkey_bindings()
+        .into_iter()
+        .map(|(hotkey, _)| hotkey)
+        .collect();
+    let started = RecorderService::start_recording(
+        Some(app_handle.clone()),
+        state.repository.clone(),
+        session_id,
+        &state.config.journal_dir,
+        Box::new(RdevEventSource),
+        options,
+        hotkeys,
+    );
+    let recorder = match started {
+        Ok(recorder) => recorder,
+        Err(e) => {
+            *state.is_recording.lock().unwrap() = false;
+            let repository = state.repository.lock().await;
+            if let Err(e) = repository.set_session_status(session_id, SessionStatus::Aborted).await {
+                eprintln!("Failed to mark session {} aborted: {}", session_id, e);
+            }
+            return Err(e);
         }
-    });
+    };
+    *state.recorder.lock().unwrap() = Some(recorder);
     println!("Started recording session: {}", session_id);
+    if RecorderService::privacy() {
+        // The recorder is already running, so don't fail the start over the flag
+        let repository = state.repository.lock().await;
+        if let Err(e) = repository.mark_session_redacted(session_id).await {
+            eprintln!("Failed to mark session {} redacted: {}", session_id, e);
+        }
+    }
+    emit_recording_state(state, &app_handle);
+    if limited {
+        watch_limits(app_handle, session_id);
+    }
 
     Ok(session_id)
 }
 
-#[tauri::command]
-pub async fn stop_recording(state: State<'_, AppState>) -> Result<String, String> {
-    {
-        let mut is_recording = state.is_recording.lock().unwrap();
-        if !*is_recording {
-            return Err(AppError::NotRecording.to_string());
+/// Emit `recording-countdown` once a second from `seconds` down to 0. Fails when the
+/// countdown is cancelled by stopping the recording.
+async fn count_down(state: &AppState, app_handle: &tauri::AppHandle, seconds: u32) -> AppResult<()> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    *state.countdown.lock().unwrap() = Some(cancelled.clone());
+
+    for remaining in (1..=seconds).rev() {
+        let _ = app_handle.emit("recording-countdown", remaining);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        if cancelled.load(Ordering::SeqCst) {
+            break;
         }
-        *is_recording = false;
     }
 
-    // 等待一下确保所有事件都被记录
-    // 等待后台把已发送事件写完（最多等 3 秒）
-    if let Some(counter) = state.recorder_in_flight.lock().await.as_ref() {
-        let start = std::time::Instant::now();
-        while counter.load(std::sync::atomic::Ordering::SeqCst) > 0 {
-            if start.elapsed().as_secs() >= 3 {
-                break;
+    // Decide under the recording flag so a concurrent stop either cancels or waits for the start
+    let _is_recording = state.is_recording.lock().unwrap();
+    state.countdown.lock().unwrap().take();
+    if cancelled.load(Ordering::SeqCst) {
+        return Err(AppError::RecordingError("Countdown cancelled".to_string()));
+    }
+    let _ = app_handle.emit("recording-countdown", 0);
+    Ok(())
+}
+
+/// Check the auto-stop limits of recording `session_id` every `LIMIT_POLL` and finish it
+/// once one is reached. Ends on its own when that recording is stopped.
+fn watch_limits(app_handle: tauri::AppHandle, session_id: i64) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(LIMIT_POLL).await;
+            let state = app_handle.state::<AppState>();
+            let reason = match state.recorder.lock().unwrap().as_ref() {
+                Some(recorder) if recorder.session_id() == session_id => recorder.limit_reached(),
+                _ => return,
+            };
+            let Some(reason) = reason else {
+                continue;
+            };
+
+            match finish_recording(&state, &app_handle).await {
+                Ok(summary) => println!("Auto-stopped ({:?}): {}", reason, summary),
+                Err(e) => eprintln!("Auto-stop failed: {}", e),
             }
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            let _ = app_handle.emit("recording-auto-stopped", reason);
+            return;
         }
+    });
+}
+
+/// Stop the current recording. Returns once every captured event has been saved.
+/// During a countdown the recording is cancelled before it starts.
+pub async fn finish_recording(state: &AppState, app_handle: &tauri::AppHandle) -> AppResult<String> {
+    let recorder = {
+        let mut is_recording = state.is_recording.lock().unwrap();
+        if let Some(cancelled) = state.countdown.lock().unwrap().take() {
+            cancelled.store(true, Ordering::SeqCst);
+            *is_recording = false;
+            drop(is_recording);
+            emit_recording_state(state, app_handle);
+            return Ok("Recording cancelled during countdown".to_string());
+        }
+        let recorder = state.recorder.lock().unwrap().take();
+        match recorder {
+            Some(recorder) if *is_recording => {
+                *is_recording = false;
+                recorder
+            }
+            _ => return Err(AppError::NotRecording),
+        }
+    };
+    let session_id = recorder.session_id();
+    let trim = recorder.options().trim_after_last_click;
+
+    // 等待后台把最后一批事件写完并退出
+    recorder.stop();
+    // Read after stopping, which also counts a trailing move that never got its click
+    let dropped = recorder.dropped_moves();
+    emit_recording_state(state, app_handle);
+    let closed = tokio::task::spawn_blocking(move || recorder.close())
+        .await
+        .map_err(|e| AppError::RecordingError(e.to_string()))
+        .and_then(|closed| closed);
+
+    // 清除当前会话
+    {
+        let mut current_session = state.current_session_id.lock().unwrap();
+        *current_session = None;
     }
 
-    // 获取当前会话ID
-    let session_id = {
-        let current_session = state.current_session_id.lock().unwrap();
-        current_session.ok_or(AppError::NoActiveSession.to_string())?
+    let stats = match closed {
+        Ok(stats) => stats,
+        Err(e) => {
+            // Unsaved batches stay in the journal and are replayed at the next start
+            let repository = state.repository.lock().await;
+            if let Err(status_err) = repository.set_session_status(session_id, SessionStatus::Aborted).await {
+                eprintln!("Failed to mark session {} aborted: {}", session_id, status_err);
+            }
+            return Err(e);
+        }
     };
 
-    // 保存记录到数据库 (don't hold std guards across await)
+    // 统计并整理已保存的记录 (don't hold std guards across await)
     let count = {
         let repository = state.repository.lock().await;
-        RecorderService::save_recording(session_id, &**repository)
-            .await
-            .map_err(|e| e.to_string())?
+        // Every event is saved by now, so a failed trim or count leaves the session complete
+        repository.set_session_status(session_id, SessionStatus::Completed).await?;
+        if trim {
+            let trimmed = RecorderService::trim_after_last_click(session_id, &**repository).await?;
+            println!("Trimmed {} events after the last click", trimmed);
+        }
+        RecorderService::save_recording(session_id, &**repository).await?
     };
 
-    // 清除当前会话
+    let summary = if stats.dropped > 0 {
+        format!(
+            "Saved {} events to session {} ({} mouse moves dropped, {} events lost to overflow)",
+            count, session_id, dropped, stats.dropped
+        )
+    } else {
+        format!(
+            "Saved {} events to session {} ({} mouse moves dropped)",
+            count, session_id, dropped
+        )
+    };
+    println!("{}", summary);
+
+    Ok(summary)
+}
+
+pub fn set_recording_paused(
+    state: &AppState,
+    app_handle: &tauri::AppHandle,
+    paused: bool,
+) -> AppResult<()> {
     {
-        let mut current_session = state.current_session_id.lock().unwrap();
-        *current_session = None;
+        let recorder = state.recorder.lock().unwrap();
+        let recorder = recorder.as_ref().ok_or(AppError::NotRecording)?;
+        if paused {
+            recorder.pause();
+        } else {
+            recorder.resume();
+        }
+    }
+    emit_recording_state(state, app_handle);
+    Ok(())
+}
+
+/// Turn privacy mode on or off. The running recording, if any, is flagged as containing
+/// redacted input.
+pub async fn set_privacy(state: &AppState, app_handle: &tauri::AppHandle, enabled: bool) -> AppResult<()> {
+    RecorderService::set_privacy(enabled);
+    emit_recording_state(state, app_handle);
+    let session_id = *state.current_session_id.lock().unwrap();
+    if let (true, Some(session_id)) = (enabled, session_id) {
+        let repository = state.repository.lock().await;
+        repository.mark_session_redacted(session_id).await?;
+    }
+    Ok(())
+}
+
+/// React to a global hotkey. Called on the listener thread, so async work is spawned.
+pub fn handle_hotkey(app_handle: &tauri::AppHandle, action: HotkeyAction) {
+    let Some(state) = app_handle.try_state::<AppState>() else {
+        return;
+    };
+
+    match action {
+        HotkeyAction::AbortPlayback => HotkeyService::abort_playback(&state.playback),
+        HotkeyAction::TogglePause => {
+            let paused = state
+                .recorder
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|recorder| recorder.is_paused());
+            if let Err(e) = set_recording_paused(&state, app_handle, !paused) {
+                eprintln!("Pause hotkey ignored: {}", e);
+            }
+        }
+        HotkeyAction::TogglePrivacy => {
+            // Switch right away so the very next keys are redacted; only the flag on the
+            // session waits for the database
+            let enabled = !RecorderService::privacy();
+            RecorderService::set_privacy(enabled);
+            emit_recording_state(&state, app_handle);
+            let session_id = *state.current_session_id.lock().unwrap();
+            if let (true, Some(session_id)) = (enabled, session_id) {
+                let app_handle = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    let state = app_handle.state::<AppState>();
+                    let repository = state.repository.lock().await;
+                    if let Err(e) = repository.mark_session_redacted(session_id).await {
+                        eprintln!("Failed to mark session {} redacted: {}", session_id, e);
+                    }
+                });
+            }
+        }
+        HotkeyAction::ToggleRecording => {
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let state = app_handle.state::<AppState>();
+                let recording = *state.is_recording.lock().unwrap();
+                let result = if recording {
+                    finish_recording(&state, &app_handle).await.map(|_| ())
+                } else {
+                    let options = state.recording_options.lock().unwrap().clone();
+                    begin_recording(&state, app_handle.clone(), "New Session", Some("Started by hotkey"), options)
+                        .await
+                        .map(|_| ())
+                };
+                if let Err(e) = result {
+                    eprintln!("Recording hotkey failed: {}", e);
+                }
+            });
+        }
     }
-    // 清除 recorder_in_flight 插槽
+}
+
+fn emit_recording_state(state: &AppState, app_handle: &tauri::AppHandle) {
+    let status = RecordingStatus {
+        recording: *state.is_recording.lock().unwrap(),
+        paused: state
+            .recorder
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|recorder| recorder.is_paused()),
+        privacy: RecorderService::privacy(),
+        session_id: *state.current_session_id.lock().unwrap(),
+    };
+    let _ = app_handle.emit("recording-state", status);
+}
+
+#[tauri::command]
+pub async fn play_recording(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+    session_id: i64,
+    options: Option<PlaybackOptions>,
+) -> Result<String, String> {
+    // Reserve the slot under the same lock as the check, so a second request arriving
+    // while this one loads can't start a playback nothing could stop
     {
-        let mut slot = state.recorder_in_flight.lock().await;
-        *slot = None;
+        let slot = state.playback.lock().unwrap();
+        let active = slot.as_ref().is_some_and(|current| current.is_active());
+        if active || state.playback_starting.swap(true, Ordering::SeqCst) {
+            return Err(AppError::AlreadyPlaying.to_string());
+        }
     }
-    println!("Saved {} events to session {}", count, session_id);
 
-    Ok(format!("Saved {} events to session {}", count, session_id))
+    // 只在加载事件期间持有 repository 锁，回放本身在后台线程运行
+    let started = {
+        let repository = state.repository.lock().await;
+        PlayerService::play_session(
+            session_id,
+            &**repository,
+            options.unwrap_or_default(),
+            Some(app_handle),
+        )
+        .await
+    };
+
+    {
+        let mut slot = state.playback.lock().unwrap();
+        state.playback_starting.store(false, Ordering::SeqCst);
+        *slot = Some(started.map_err(|e| e.to_string())?);
+    }
+
+    Ok(format!("Playback of session {} started", session_id))
 }
 
 #[tauri::command]
-pub async fn play_recording(state: State<'_, AppState>, session_id: i64) -> Result<String, String> {
+pub async fn dry_run_playback(
+    state: State<'_, AppState>,
+    session_id: i64,
+    options: Option<PlaybackOptions>,
+) -> Result<DryRunReport, String> {
     let repository = state.repository.lock().await;
-    PlayerService::play_session(session_id, &**repository)
+    PlayerService::dry_run(session_id, &**repository, options.unwrap_or_default())
         .await
-        .map_err(|e| e.to_string())?;
-    Ok("Playback completed".to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn pause_playback(state: State<'_, AppState>) -> Result<(), String> {
+    let playback = state.playback.lock().unwrap();
+    let control = playback.as_ref().ok_or(AppError::NotPlaying.to_string())?;
+    control.pause().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn resume_playback(state: State<'_, AppState>) -> Result<(), String> {
+    let playback = state.playback.lock().unwrap();
+    let control = playback.as_ref().ok_or(AppError::NotPlaying.to_string())?;
+    control.resume().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn stop_playback(state: State<'_, AppState>) -> Result<(), String> {
+    let playback = state.playback.lock().unwrap();
+    let control = playback.as_ref().ok_or(AppError::NotPlaying.to_string())?;
+    control.stop().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_playback_status(
+    state: State<'_, AppState>,
+) -> Result<Option<PlaybackStatus>, String> {
+    let playback = state.playback.lock().unwrap();
+    Ok(playback.as_ref().map(|control| control.status()))
 }
 
 #[tauri::command]
@@ -1,9 +1,30 @@
+use crate::models::{Hotkey, HotkeyAction};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use tauri::Manager;
 
+/// 默认的紧急停止热键
+const DEFAULT_PANIC_HOTKEY: &str = "Ctrl+Shift+Escape";
+/// 默认的开始/停止录制热键
+const DEFAULT_RECORD_HOTKEY: &str = "Ctrl+Shift+R";
+/// 默认的暂停/继续录制热键
+const DEFAULT_PAUSE_HOTKEY: &str = "Ctrl+Shift+P";
+/// 默认的隐私模式开关热键
+const DEFAULT_PRIVACY_HOTKEY: &str = "Ctrl+Shift+H";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub database: DatabaseConfig,
+    /// Global key combination that aborts a running playback
+    pub panic_hotkey: Hotkey,
+    /// Global key combination that starts or stops a recording
+    pub record_hotkey: Hotkey,
+    /// Global key combination that pauses or resumes the current recording
+    pub pause_hotkey: Hotkey,
+    /// Global key combination that turns privacy mode on or off
+    pub privacy_hotkey: Hotkey,
+    /// Where recordings journal their events before saving them
+    pub journal_dir: PathBuf,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,16 +41,11 @@ pub enum DatabaseConfig {
 
 impl AppConfig {
     pub fn load_from_env(app_handle: &tauri::AppHandle) -> Self {
-        // 从环境变量读取数据库配置
-        if let Ok(pg_conn) = std::env::var("DATABASE_URL") {
-            #[cfg(feature = "postgres")]
-            return AppConfig {
-                database: DatabaseConfig::PostgreSQL {
-                    connection_string: pg_conn,
-                },
-            };
-        }
-        
+        let panic_hotkey = Self::hotkey_from_env("MICROPLATTER_PANIC_HOTKEY", DEFAULT_PANIC_HOTKEY);
+        let record_hotkey = Self::hotkey_from_env("MICROPLATTER_RECORD_HOTKEY", DEFAULT_RECORD_HOTKEY);
+        let pause_hotkey = Self::hotkey_from_env("MICROPLATTER_PAUSE_HOTKEY", DEFAULT_PAUSE_HOTKEY);
+        let privacy_hotkey = Self::hotkey_from_env("MICROPLATTER_PRIVACY_HOTKEY", DEFAULT_PRIVACY_HOTKEY);
+
         // 默认使用 SQLite，并选择平台合适的位置以避免在开发时触发 watcher 重建
         // Linux: $XDG_DATA_HOME 或 $HOME/.local/share
         // macOS: ~/Library/Application Support
@@ -93,10 +109,56 @@ impl AppConfig {
             }
         }
 
+        // 事件日志放在数据库旁边
+        let journal_dir = db_path
+            .parent()
+            .map_or_else(|| PathBuf::from("journal"), |parent| parent.join("journal"));
+
+        // 从环境变量读取数据库配置
+        if let Ok(pg_conn) = std::env::var("DATABASE_URL") {
+            #[cfg(feature = "postgres")]
+            return AppConfig {
+                database: DatabaseConfig::PostgreSQL {
+                    connection_string: pg_conn,
+                },
+                panic_hotkey,
+                record_hotkey,
+                pause_hotkey,
+                privacy_hotkey,
+                journal_dir,
+            };
+        }
+
         AppConfig {
             database: DatabaseConfig::SQLite {
                 path: db_path.to_string_lossy().to_string(),
             },
+            panic_hotkey,
+            record_hotkey,
+            pause_hotkey,
+            privacy_hotkey,
+            journal_dir,
+        }
+    }
+
+    /// Every configured global hotkey with the action it triggers.
+    pub fn hotkey_bindings(&self) -> Vec<(Hotkey, HotkeyAction)> {
+        vec![
+            (self.panic_hotkey.clone(), HotkeyAction::AbortPlayback),
+            (self.record_hotkey.clone(), HotkeyAction::ToggleRecording),
+            (self.pause_hotkey.clone(), HotkeyAction::TogglePause),
+            (self.privacy_hotkey.clone(), HotkeyAction::TogglePrivacy),
+        ]
+    }
+
+    /// Read a hotkey from the environment, falling back to `default` when unset or invalid.
+    fn hotkey_from_env(var: &str, default: &str) -> Hotkey {
+        if let Ok(spec) = std::env::var(var) {
+            match Hotkey::parse(&spec) {
+                Ok(hotkey) => return hotkey,
+                Err(e) => eprintln!("Ignoring {}: {}", var, e),
+            }
         }
+        Hotkey::parse(default).expect("default hotkey must parse")
     }
 }
\ No newline at end of file
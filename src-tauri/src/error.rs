@@ -23,6 +23,21 @@ pub enum AppError {
     #[error("Playback error: {0}")]
     PlaybackError(String),
     
+    #[error("Already playing")]
+    AlreadyPlaying,
+    
+    #[error("Not playing")]
+    NotPlaying,
+    
+    #[error("Playback stopped")]
+    PlaybackStopped,
+    
+    #[error("Invalid hotkey: {0}")]
+    InvalidHotkey(String),
+    
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+    
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 }
@@ -7,6 +7,7 @@ mod state;
 mod error;
 
 use state::AppState;
+use services::HotkeyService;
 use commands::*;
 use tauri::Manager;
 
@@ -19,6 +20,13 @@ pub fn run() {
             tauri::async_runtime::block_on(async move {
                 match AppState::new(&handle).await {
                     Ok(state) => {
+                        let hotkey_handle = handle.clone();
+                        let started = HotkeyService::start(state.config.hotkey_bindings(), move |action| {
+                            handle_hotkey(&hotkey_handle, action)
+                        });
+                        if let Err(e) = started {
+                            eprintln!("Failed to start hotkeys: {}", e);
+                        }
                         handle.manage(state);
                     }
                     Err(e) => {
@@ -33,8 +41,17 @@ pub fn run() {
             // 录制命令
             start_recording,
             stop_recording,
+            pause_recording,
+            resume_recording,
+            set_privacy_mode,
             play_recording,
             get_recording_status,
+            // 回放控制
+            dry_run_playback,
+            pause_playback,
+            resume_playback,
+            stop_playback,
+            get_playback_status,
             // 会话命令
             list_sessions,
             get_session,
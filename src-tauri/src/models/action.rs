@@ -1,6 +1,9 @@
+use crate::error::{AppError, AppResult};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "type")]
 pub enum Action {
     #[serde(rename = "mouse_move")]
@@ -27,17 +30,45 @@ pub enum Action {
         x: i32, 
         y: i32 
     },
+    /// Full press and release of a key, as stored by older recordings.
     #[serde(rename = "key_press")]
     KeyPress { 
-        key: String 
+        key: Key 
     },
+    #[serde(rename = "key_down")]
+    KeyDown { 
+        key: Key,
+        /// Characters the press typed, if any. Playback can enter non-ASCII text in place
+        /// of the key press, see `PlaybackOptions::type_text`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    #[serde(rename = "key_up")]
+    KeyUp { 
+        key: Key 
+    },
+    /// Characters produced by a key press, replayed as text independent of the keyboard layout.
+    #[serde(rename = "text")]
+    Text { 
+        text: String 
+    },
+    /// Key input recorded in privacy mode; only its timing is kept. Playback pauses here
+    /// so the user can enter it by hand.
+    #[serde(rename = "redacted")]
+    Redacted,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum MouseButton {
     Left,
     Right,
     Middle,
+    /// Side button, usually "browser back".
+    Back,
+    /// Side button, usually "browser forward".
+    Forward,
+    /// Button the platform reports only by number.
+    Other(u8),
 }
 
 impl Action {
@@ -48,6 +79,44 @@ impl Action {
             Action::MouseUp { .. } => "MouseUp",
             Action::Wheel { .. } => "Wheel",
             Action::KeyPress { .. } => "KeyPress",
+            Action::KeyDown { .. } => "KeyDown",
+            Action::KeyUp { .. } => "KeyUp",
+            Action::Text { .. } => "Text",
+            Action::Redacted => "Redacted",
+        }
+    }
+    
+    /// Screen position the action happens at, if it has one.
+    pub fn position(&self) -> Option<(i32, i32)> {
+        match self {
+            Action::MouseMove { x, y }
+            | Action::MouseDown { x, y, .. }
+            | Action::MouseUp { x, y, .. }
+            | Action::Wheel { x, y, .. } => Some((*x, *y)),
+            _ => None,
+        }
+    }
+    
+    /// Copy of this action with its screen position passed through `f`.
+    pub fn map_position(&self, f: impl Fn(i32, i32) -> (i32, i32)) -> Action {
+        match self.clone() {
+            Action::MouseMove { x, y } => {
+                let (x, y) = f(x, y);
+                Action::MouseMove { x, y }
+            }
+            Action::MouseDown { button, x, y } => {
+                let (x, y) = f(x, y);
+                Action::MouseDown { button, x, y }
+            }
+            Action::MouseUp { button, x, y } => {
+                let (x, y) = f(x, y);
+                Action::MouseUp { button, x, y }
+            }
+            Action::Wheel { delta_x, delta_y, x, y } => {
+                let (x, y) = f(x, y);
+                Action::Wheel { delta_x, delta_y, x, y }
+            }
+            other => other,
         }
     }
 }
@@ -58,15 +127,285 @@ impl MouseButton {
             rdev::Button::Left => MouseButton::Left,
             rdev::Button::Right => MouseButton::Right,
             rdev::Button::Middle => MouseButton::Middle,
-            _ => MouseButton::Left,
+            // X11 numbers the side buttons 8 and 9, Windows reports XBUTTON1 and XBUTTON2
+            #[cfg(all(unix, not(target_os = "macos")))]
+            rdev::Button::Unknown(8) => MouseButton::Back,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            rdev::Button::Unknown(9) => MouseButton::Forward,
+            #[cfg(target_os = "windows")]
+            rdev::Button::Unknown(1) => MouseButton::Back,
+            #[cfg(target_os = "windows")]
+            rdev::Button::Unknown(2) => MouseButton::Forward,
+            rdev::Button::Unknown(code) => MouseButton::Other(*code),
         }
     }
     
-    pub fn to_enigo(&self) -> enigo::Button {
+    /// Map to an enigo button, failing for buttons that cannot be synthesized here
+    /// rather than clicking a different one.
+    pub fn to_enigo(&self) -> AppResult<enigo::Button> {
+        match self {
+            MouseButton::Left => Ok(enigo::Button::Left),
+            MouseButton::Right => Ok(enigo::Button::Right),
+            MouseButton::Middle => Ok(enigo::Button::Middle),
+            #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+            MouseButton::Back => Ok(enigo::Button::Back),
+            #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+            MouseButton::Forward => Ok(enigo::Button::Forward),
+            other => Err(AppError::PlaybackError(format!(
+                "Mouse button {:?} cannot be replayed on this platform",
+                other
+            ))),
+        }
+    }
+}
+
+macro_rules! keys {
+    ($($name:ident),* $(,)?) => {
+        /// A keyboard key as stored in recordings.
+        ///
+        /// Named keys mirror `rdev::Key` one to one. Serialized as a plain string using the
+        /// names rdev's `Debug` output gives (`KeyA`, `ShiftLeft`, `Unknown(42)`), which is
+        /// what sessions recorded before this type existed contain, so they still load.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub enum Key {
+            $($name,)*
+            /// Platform keycode rdev has no name for.
+            Unknown(u32),
+            /// Raw keycode (a scancode on Windows) injected as is, bypassing the keyboard layout.
+            Raw(u16),
+        }
+
+        impl Key {
+            pub fn from_rdev(key: rdev::Key) -> Self {
+                match key {
+                    $(rdev::Key::$name => Key::$name,)*
+                    rdev::Key::Unknown(code) => Key::Unknown(code),
+                }
+            }
+
+            /// The rdev key, `None` for raw keycodes which rdev cannot express.
+            pub fn to_rdev(self) -> Option<rdev::Key> {
+                match self {
+                    $(Key::$name => Some(rdev::Key::$name),)*
+                    Key::Unknown(code) => Some(rdev::Key::Unknown(code)),
+                    Key::Raw(_) => None,
+                }
+            }
+
+            fn name(self) -> Option<&'static str> {
+                match self {
+                    $(Key::$name => Some(stringify!($name)),)*
+                    _ => None,
+                }
+            }
+
+            fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($name) => Some(Key::$name),)*
+                    _ => None,
+                }
+            }
+
+            /// Look up a key name regardless of case, e.g. `escape` or `f12`.
+            pub fn from_name_ignore_case(name: &str) -> Option<Self> {
+                $(if name.eq_ignore_ascii_case(stringify!($name)) {
+                    return Some(Key::$name);
+                })*
+                None
+            }
+        }
+    };
+}
+
+keys! {
+    Alt, AltGr, Backspace, CapsLock, ControlLeft, ControlRight, Delete, DownArrow, End, Escape,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Home, LeftArrow, MetaLeft, MetaRight, PageDown, PageUp, Return, RightArrow,
+    ShiftLeft, ShiftRight, Space, Tab, UpArrow, PrintScreen, ScrollLock, Pause, NumLock,
+    BackQuote, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, Num0, Minus, Equal,
+    KeyQ, KeyW, KeyE, KeyR, KeyT, KeyY, KeyU, KeyI, KeyO, KeyP, LeftBracket, RightBracket,
+    KeyA, KeyS, KeyD, KeyF, KeyG, KeyH, KeyJ, KeyK, KeyL, SemiColon, Quote, BackSlash,
+    IntlBackslash, KeyZ, KeyX, KeyC, KeyV, KeyB, KeyN, KeyM, Comma, Dot, Slash, Insert,
+    KpReturn, KpMinus, KpPlus, KpMultiply, KpDivide,
+    Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6, Kp7, Kp8, Kp9, KpDelete, Function,
+}
+
+/// How a `Key` is synthesized through enigo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnigoKey {
+    Key(enigo::Key),
+    Raw(u16),
+}
+
+impl Key {
+    /// Whether this is a Ctrl, Shift, Alt or Meta key.
+    pub fn is_modifier(self) -> bool {
+        matches!(
+            self,
+            Key::ControlLeft
+                | Key::ControlRight
+                | Key::ShiftLeft
+                | Key::ShiftRight
+                | Key::Alt
+                | Key::AltGr
+                | Key::MetaLeft
+                | Key::MetaRight
+        )
+    }
+
+    /// Map to an enigo key. Returns `None` when the key cannot be synthesized on this platform.
+    pub fn to_enigo(self) -> Option<EnigoKey> {
+        use enigo::Key as E;
+
+        let key = match self {
+            Key::Raw(code) => return Some(EnigoKey::Raw(code)),
+            // rdev reports virtual key codes on Windows and hardware keycodes elsewhere
+            #[cfg(target_os = "windows")]
+            Key::Unknown(code) => E::Other(code),
+            #[cfg(not(target_os = "windows"))]
+            Key::Unknown(code) => return u16::try_from(code).ok().map(EnigoKey::Raw),
+            Key::Alt => E::Alt,
+            Key::Backspace => E::Backspace,
+            Key::CapsLock => E::CapsLock,
+            Key::ControlLeft => E::LControl,
+            Key::ControlRight => E::RControl,
+            Key::Delete | Key::KpDelete => E::Delete,
+            Key::DownArrow => E::DownArrow,
+            Key::End => E::End,
+            Key::Escape => E::Escape,
+            Key::F1 => E::F1,
+            Key::F2 => E::F2,
+            Key::F3 => E::F3,
+            Key::F4 => E::F4,
+            Key::F5 => E::F5,
+            Key::F6 => E::F6,
+            Key::F7 => E::F7,
+            Key::F8 => E::F8,
+            Key::F9 => E::F9,
+            Key::F10 => E::F10,
+            Key::F11 => E::F11,
+            Key::F12 => E::F12,
+            Key::Home => E::Home,
+            Key::LeftArrow => E::LeftArrow,
+            Key::MetaLeft | Key::MetaRight => E::Meta,
+            Key::PageDown => E::PageDown,
+            Key::PageUp => E::PageUp,
+            Key::Return | Key::KpReturn => E::Return,
+            Key::RightArrow => E::RightArrow,
+            Key::ShiftLeft => E::LShift,
+            Key::ShiftRight => E::RShift,
+            Key::Space => E::Space,
+            Key::Tab => E::Tab,
+            Key::UpArrow => E::UpArrow,
+            #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+            Key::Insert => E::Insert,
+            #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+            Key::PrintScreen => E::Print,
+            #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+            Key::Pause => E::Pause,
+            #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+            Key::NumLock => E::Numlock,
+            #[cfg(target_os = "windows")]
+            Key::ScrollLock => E::Scroll,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            Key::ScrollLock => E::ScrollLock,
+            #[cfg(target_os = "macos")]
+            Key::Function => E::Function,
+            // 字母、数字和符号键按字符回放
+            other => E::Unicode(other.to_char()?),
+        };
+        Some(EnigoKey::Key(key))
+    }
+
+    /// The character a printable key types without modifiers.
+    pub fn to_char(self) -> Option<char> {
+        let name = self.name()?;
+        if let Some(letter) = name.strip_prefix("Key") {
+            return letter.chars().next().map(|c| c.to_ascii_lowercase());
+        }
+        if let Some(digit) = name.strip_prefix("Num").or_else(|| name.strip_prefix("Kp")) {
+            let mut chars = digit.chars();
+            if let (Some(c @ '0'..='9'), None) = (chars.next(), chars.next()) {
+                return Some(c);
+            }
+        }
+
+        let c = match self {
+            Key::BackQuote => '`',
+            Key::Minus | Key::KpMinus => '-',
+            Key::Equal => '=',
+            Key::KpPlus => '+',
+            Key::KpMultiply => '*',
+            Key::Slash | Key::KpDivide => '/',
+            Key::LeftBracket => '[',
+            Key::RightBracket => ']',
+            Key::SemiColon => ';',
+            Key::Quote => '\'',
+            Key::BackSlash | Key::IntlBackslash => '\\',
+            Key::Comma => ',',
+            Key::Dot => '.',
+            _ => return None,
+        };
+        Some(c)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            MouseButton::Left => enigo::Button::Left,
-            MouseButton::Right => enigo::Button::Right,
-            MouseButton::Middle => enigo::Button::Middle,
+            Key::Unknown(code) => write!(f, "Unknown({})", code),
+            Key::Raw(code) => write!(f, "Raw({})", code),
+            named => f.write_str(named.name().unwrap_or_default()),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> AppResult<Self> {
+        let code = |prefix: &str| s.strip_prefix(prefix).and_then(|rest| rest.strip_suffix(')'));
+        let parsed = if let Some(code) = code("Unknown(") {
+            code.parse().ok().map(Key::Unknown)
+        } else if let Some(code) = code("Raw(") {
+            code.parse().ok().map(Key::Raw)
+        } else {
+            Key::from_name(s)
+        };
+        parsed.ok_or_else(|| AppError::InvalidKey(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        s.parse()
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> String {
+        key.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_key_rows_round_trip() {
+        for (row, action) in [
+            (r#"{"type":"key_press","key":"ShiftLeft"}"#, Action::KeyPress { key: Key::ShiftLeft }),
+            (r#"{"type":"key_press","key":"Unknown(42)"}"#, Action::KeyPress { key: Key::Unknown(42) }),
+            (r#"{"type":"key_press","key":"KeyA"}"#, Action::KeyPress { key: Key::KeyA }),
+        ] {
+            let parsed: Action = serde_json::from_str(row).unwrap();
+            assert_eq!(parsed, action);
+            assert_eq!(serde_json::to_string(&parsed).unwrap(), row);
         }
+        assert!(serde_json::from_str::<Action>(r#"{"type":"key_press","key":"Nope"}"#).is_err());
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use super::Action;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct EventRecord {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<i64>,
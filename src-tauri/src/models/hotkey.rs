@@ -0,0 +1,120 @@
+use crate::error::{AppError, AppResult};
+use crate::models::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+/// What a global hotkey does when pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    /// Stop the running playback
+    AbortPlayback,
+    /// Start a new recording, or stop the current one
+    ToggleRecording,
+    /// Pause or resume the current recording
+    TogglePause,
+    /// Turn privacy mode, which redacts recorded keys, on or off
+    TogglePrivacy,
+}
+
+/// A global key combination such as `Ctrl+Shift+Escape`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub meta: bool,
+    pub key: Key,
+}
+
+impl Hotkey {
+    /// Parse a `+`-separated combination, e.g. `Ctrl+Shift+Esc` or `Alt+F12`.
+    pub fn parse(spec: &str) -> AppResult<Self> {
+        let (mut ctrl, mut shift, mut alt, mut meta) = (false, false, false, false);
+        let mut key = None;
+
+        for part in spec.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => ctrl = true,
+                "shift" => shift = true,
+                "alt" | "option" => alt = true,
+                "meta" | "super" | "cmd" | "win" => meta = true,
+                "" => {}
+                _ if key.is_some() => {
+                    return Err(AppError::InvalidHotkey(format!(
+                        "'{}' has more than one non-modifier key",
+                        spec
+                    )))
+                }
+                "esc" => key = Some(Key::Escape),
+                "enter" => key = Some(Key::Return),
+                lower => key = Some(Self::key_name(part, lower)?),
+            }
+        }
+
+        let key = key.ok_or_else(|| {
+            AppError::InvalidHotkey(format!("'{}' has no key besides modifiers", spec))
+        })?;
+        Ok(Hotkey {
+            ctrl,
+            shift,
+            alt,
+            meta,
+            key,
+        })
+    }
+
+    /// Resolve a user-typed key: single letters become `KeyX`, single digits `NumX`,
+    /// anything else has to be a key name as recorded, in any case (e.g. `F12`, `escape`).
+    fn key_name(part: &str, lower: &str) -> AppResult<Key> {
+        let mut chars = lower.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(c @ 'a'..='z'), None) => format!("Key{}", c.to_ascii_uppercase()).parse().ok(),
+            (Some(c @ '0'..='9'), None) => format!("Num{}", c).parse().ok(),
+            _ => Key::from_name_ignore_case(part).or_else(|| part.parse().ok()),
+        };
+        key.ok_or_else(|| AppError::InvalidHotkey(format!("unknown key '{}'", part)))
+    }
+
+    /// Whether pressing `key` while `held` keys are down triggers this hotkey.
+    pub fn matches(&self, key: Key, held: &HashSet<Key>) -> bool {
+        let held_any = |keys: &[Key]| keys.iter().any(|k| held.contains(k));
+        key == self.key
+            && self.ctrl == held_any(&[Key::ControlLeft, Key::ControlRight])
+            && self.shift == held_any(&[Key::ShiftLeft, Key::ShiftRight])
+            && self.alt == held_any(&[Key::Alt, Key::AltGr])
+            && self.meta == held_any(&[Key::MetaLeft, Key::MetaRight])
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (on, name) in [
+            (self.ctrl, "Ctrl"),
+            (self.shift, "Shift"),
+            (self.alt, "Alt"),
+            (self.meta, "Meta"),
+        ] {
+            if on {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_key_names_in_any_case() {
+        let hotkey = Hotkey::parse("ctrl+shift+escape").unwrap();
+        assert_eq!(hotkey, Hotkey::parse("Ctrl+Shift+Escape").unwrap());
+        assert!(hotkey.ctrl && hotkey.shift && hotkey.key == Key::Escape);
+        assert_eq!(Hotkey::parse("f12").unwrap().key, Key::F12);
+        assert_eq!(Hotkey::parse("Alt+PAGEDOWN").unwrap().key, Key::PageDown);
+        assert_eq!(Hotkey::parse("Ctrl+Unknown(42)").unwrap().key, Key::Unknown(42));
+        assert!(Hotkey::parse("Ctrl+Nope").is_err());
+    }
+}
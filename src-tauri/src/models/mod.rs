@@ -1,7 +1,13 @@
 pub mod action;
 pub mod event;
 pub mod session;
+pub mod playback;
+pub mod recording;
+pub mod hotkey;
 
-pub use action::{Action, MouseButton};
+pub use action::{Action, EnigoKey, Key, MouseButton};
 pub use event::EventRecord;
-pub use session::{Session, SessionResponse, CreateSessionRequest, UpdateSessionRequest};
\ No newline at end of file
+pub use session::{Session, SessionResponse, SessionStatus, ScreenGeometry, CreateSessionRequest, UpdateSessionRequest};
+pub use playback::{CoordinateMapping, DryRunReport, PlaybackOptions, PlaybackState, PlaybackStatus, PlaybackWarning, RepeatMode};
+pub use recording::{AutoStopReason, OverflowPolicy, QueueStats, RecordingOptions, RecordingStatus, Redaction};
+pub use hotkey::{Hotkey, HotkeyAction};
\ No newline at end of file
@@ -0,0 +1,164 @@
+use super::EventRecord;
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// 回放选项 - 由前端传入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaybackOptions {
+    /// Multiplier applied to the recorded gaps, e.g. 2.0 replays twice as fast.
+    pub speed: f64,
+    /// Ignore the recorded gaps and inject events back to back.
+    pub as_fast_as_possible: bool,
+    /// How many times the session is replayed
+    pub repeat: RepeatMode,
+    /// Pause between two iterations, in milliseconds
+    pub iteration_delay_ms: u64,
+    /// How recorded coordinates are adapted to the current screen
+    pub coordinate_mapping: CoordinateMapping,
+    /// Enter non-ASCII text recorded with key presses (accents, IME input) instead of
+    /// pressing the keys. Independent of the keyboard layout, but doesn't keep how long the
+    /// keys were held; plain ASCII keys are always pressed and released as recorded.
+    pub type_text: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum CoordinateMapping {
+    /// Replay the recorded coordinates unchanged
+    None,
+    /// Stretch coordinates from the recorded screen size to the current one
+    Scale,
+    /// Shift every coordinate by a fixed amount
+    Offset { dx: i32, dy: i32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RepeatMode {
+    Once,
+    Times { count: u32 },
+    /// Keep looping until playback is stopped
+    UntilCancelled,
+}
+
+impl RepeatMode {
+    /// Total number of iterations, `None` when looping until cancelled.
+    pub fn total(&self) -> Option<u32> {
+        match self {
+            RepeatMode::Once => Some(1),
+            RepeatMode::Times { count } => Some(*count),
+            RepeatMode::UntilCancelled => None,
+        }
+    }
+
+    /// Whether another iteration follows the (1-based) `iteration` that just finished.
+    pub fn has_next(&self, iteration: u32) -> bool {
+        self.total().is_none_or(|total| iteration < total)
+    }
+}
+
+impl PlaybackOptions {
+    pub const MIN_SPEED: f64 = 0.25;
+    pub const MAX_SPEED: f64 = 10.0;
+
+    pub fn validate(&self) -> AppResult<()> {
+        if !(Self::MIN_SPEED..=Self::MAX_SPEED).contains(&self.speed) {
+            return Err(AppError::PlaybackError(format!(
+                "Speed must be between {}x and {}x, got {}x",
+                Self::MIN_SPEED,
+                Self::MAX_SPEED,
+                self.speed
+            )));
+        }
+        if self.repeat == (RepeatMode::Times { count: 0 }) {
+            return Err(AppError::PlaybackError(
+                "Repeat count must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Scale a recorded offset (relative to the first event) to playback time.
+    pub fn scale(&self, offset_ms: u128) -> Duration {
+        if self.as_fast_as_possible {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(offset_ms as f64 / 1000.0 / self.speed)
+    }
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            as_fast_as_possible: false,
+            repeat: RepeatMode::Once,
+            iteration_delay_ms: 0,
+            coordinate_mapping: CoordinateMapping::None,
+            type_text: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackState {
+    Running,
+    Paused,
+    /// Stop was requested, the player exits at its next checkpoint
+    Stopping,
+    Finished,
+    Stopped,
+    Failed,
+}
+
+impl PlaybackState {
+    pub fn is_active(&self) -> bool {
+        matches!(self, PlaybackState::Running | PlaybackState::Paused | PlaybackState::Stopping)
+    }
+}
+
+/// API 响应 - 当前回放状态
+/// Also the payload of the `playback-started`, `playback-progress`,
+/// `playback-finished` and `playback-failed` events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackStatus {
+    pub session_id: i64,
+    pub state: PlaybackState,
+    /// Number of events injected so far
+    pub event_index: usize,
+    pub total_events: usize,
+    /// Current iteration, starting at 1
+    pub iteration: u32,
+    /// `None` when repeating until cancelled
+    pub total_iterations: Option<u32>,
+    /// Playback time reached in the current iteration, speed scaling applied
+    pub elapsed_ms: u64,
+    /// Playback time of one full iteration, speed scaling applied
+    pub total_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// API 响应 - 试运行报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunReport {
+    pub session_id: i64,
+    /// Actions in injection order; `timestamp_ms` is the scheduled time from playback start
+    pub actions: Vec<EventRecord>,
+    pub total_duration_ms: u64,
+    /// Number of actions per `Action::action_type`
+    pub counts: BTreeMap<String, usize>,
+    pub warnings: Vec<PlaybackWarning>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackWarning {
+    /// Index into `DryRunReport::actions`, None for warnings about the whole run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_index: Option<usize>,
+    pub message: String,
+}
\ No newline at end of file
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+/// 录制选项 - 由前端传入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingOptions {
+    /// Drop mouse moves closer than this many pixels to the last kept move (0 keeps all)
+    pub min_move_distance: u32,
+    /// Drop mouse moves sooner than this after the last kept move (0 keeps all)
+    pub min_move_interval_ms: u64,
+    /// Keep only the last mouse move before each button or wheel event
+    pub moves_before_clicks_only: bool,
+    /// Leave out input aimed at the app's own window
+    pub exclude_app_window: bool,
+    /// After stopping, drop the mouse moves and scrolling recorded after the last click
+    pub trim_after_last_click: bool,
+    /// Events held in memory while waiting to be saved
+    pub queue_capacity: usize,
+    /// What happens to new events when the queue is full
+    pub overflow_policy: OverflowPolicy,
+    /// Seconds to count down before capturing starts (0 starts right away)
+    pub countdown_secs: u32,
+    /// Stop after this much recording time, not counting pauses
+    pub max_duration_secs: Option<u64>,
+    /// Stop once this many events have been recorded
+    pub max_events: Option<usize>,
+    /// Stop after this long without any input
+    pub idle_timeout_secs: Option<u64>,
+    /// How keys are recorded while privacy mode is on
+    pub redaction: Redaction,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        Self {
+            min_move_distance: 0,
+            min_move_interval_ms: 0,
+            moves_before_clicks_only: false,
+            exclude_app_window: false,
+            trim_after_last_click: false,
+            queue_capacity: 10_000,
+            overflow_policy: OverflowPolicy::DropOldestMoves,
+            countdown_secs: 0,
+            max_duration_secs: None,
+            max_events: None,
+            idle_timeout_secs: None,
+            redaction: Redaction::Placeholder,
+        }
+    }
+}
+
+impl RecordingOptions {
+    /// Whether any auto-stop limit is set.
+    pub fn has_limits(&self) -> bool {
+        self.max_duration_secs.is_some() || self.max_events.is_some() || self.idle_timeout_secs.is_some()
+    }
+}
+
+/// How the recorder copes with a full queue, e.g. while the database is stalled. There is
+/// no policy that waits for room: pushing happens on the shared input hub thread, so
+/// blocking there would also stall the other listeners, the panic hotkey among them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued mouse moves and scrolling; once none are left, new events
+    /// are dropped as they arrive
+    DropOldestMoves,
+    /// Write overflowing events to a file in the journal directory and save them once the
+    /// queue drains
+    SpillToDisk,
+}
+
+/// What privacy mode does with key input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Redaction {
+    /// Record a `Redacted` action in place of each key press, keeping the timing
+    Placeholder,
+    /// Leave key presses out entirely
+    Drop,
+}
+
+/// Which auto-stop limit of `RecordingOptions` ended a recording, pushed to the frontend
+/// as `recording-auto-stopped`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoStopReason {
+    MaxDuration,
+    MaxEvents,
+    Idle,
+}
+
+/// Recorder queue counters, pushed to the frontend as `recording-queue`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct QueueStats {
+    /// Waiting to be saved, in memory or spilled to disk
+    pub queued: usize,
+    pub persisted: usize,
+    /// Lost to overflow
+    pub dropped: usize,
+}
+
+/// Recording state pushed to the frontend as `recording-state` when it changes
+/// outside the UI, e.g. through a global hotkey.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingStatus {
+    pub recording: bool,
+    pub paused: bool,
+    /// Privacy mode; applies to whichever recording runs
+    pub privacy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<i64>,
+}
@@ -10,6 +10,81 @@ pub struct Session {
     pub created_at: DateTime<Utc>,
     pub event_count: i64,
     pub time_cost: f64,
+    /// Primary display at the time of recording; None for sessions recorded before it was tracked
+    pub screen: Option<ScreenGeometry>,
+    pub status: SessionStatus,
+    /// Privacy mode was on at some point while recording
+    pub contains_redacted: bool,
+}
+
+/// Where a session is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStatus {
+    /// Being recorded; left behind if the app dies mid-recording
+    Recording,
+    Completed,
+    /// Recording was cut off, e.g. by a crash, and recovered at the next start
+    Aborted,
+    /// Brought in from elsewhere rather than recorded here
+    Imported,
+}
+
+impl SessionStatus {
+    /// Value stored in the `status` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionStatus::Recording => "recording",
+            SessionStatus::Completed => "completed",
+            SessionStatus::Aborted => "aborted",
+            SessionStatus::Imported => "imported",
+        }
+    }
+
+    /// Parse a `status` column value; anything unknown counts as completed.
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "recording" => SessionStatus::Recording,
+            "aborted" => SessionStatus::Aborted,
+            "imported" => SessionStatus::Imported,
+            _ => SessionStatus::Completed,
+        }
+    }
+}
+
+/// Display the coordinates of a session refer to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScreenGeometry {
+    /// Size in the same units as recorded `Action` coordinates
+    pub width: u32,
+    pub height: u32,
+    /// OS scaling factor of the display, e.g. 2.0 on a HiDPI screen
+    pub scale_factor: f64,
+}
+
+impl ScreenGeometry {
+    /// Geometry of the primary display. The size comes from rdev so it matches the
+    /// coordinate space of recorded events; the scale factor comes from the window system.
+    pub fn detect(app_handle: Option<&tauri::AppHandle>) -> Option<Self> {
+        let (width, height) = match rdev::display_size() {
+            Ok(size) => size,
+            Err(e) => {
+                eprintln!("Failed to query display size: {:?}", e);
+                return None;
+            }
+        };
+
+        let scale_factor = app_handle
+            .and_then(|handle| handle.primary_monitor().ok().flatten())
+            .map(|monitor| monitor.scale_factor())
+            .unwrap_or(1.0);
+
+        Some(Self {
+            width: width as u32,
+            height: height as u32,
+            scale_factor,
+        })
+    }
 }
 
 /// API 响应 - 返回给前端
@@ -22,6 +97,10 @@ pub struct SessionResponse {
     pub created_at: String,
     pub event_count: i64,
     pub time_cost: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen: Option<ScreenGeometry>,
+    pub status: SessionStatus,
+    pub contains_redacted: bool,
 }
 
 /// API 请求 - 创建会话
@@ -47,6 +126,9 @@ impl From<Session> for SessionResponse {
             created_at: session.created_at.to_rfc3339(),
             event_count: session.event_count,
             time_cost: session.time_cost,
+            screen: session.screen,
+            status: session.status,
+            contains_redacted: session.contains_redacted,
         }
     }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 #[cfg(feature = "postgres")]
 use super::SessionRepository;
-use crate::models::{EventRecord, Session, SessionInfo};
+use crate::models::{EventRecord, ScreenGeometry, Session, SessionStatus};
 use crate::error::{AppError, AppResult};
 use async_trait::async_trait;
 use chrono::Utc;
@@ -21,6 +21,21 @@ impl PostgresSessionRepository {
         
         Ok(Self { pool })
     }
+    
+    fn screen_from_row(row: &tokio_postgres::Row, first: usize) -> Option<ScreenGeometry> {
+        let width: Option<i32> = row.get(first);
+        let height: Option<i32> = row.get(first + 1);
+        let scale_factor: Option<f64> = row.get(first + 2);
+        
+        match (width, height) {
+            (Some(width), Some(height)) => Some(ScreenGeometry {
+                width: width as u32,
+                height: height as u32,
+                scale_factor: scale_factor.unwrap_or(1.0),
+            }),
+            _ => None,
+        }
+    }
 }
 
 #[async_trait]
@@ -41,6 +56,15 @@ impl SessionRepository for PostgresSessionRepository {
             &[],
         ).await.map_err(|e| AppError::Database(e.into()))?;
         
+        // 旧数据库升级
+        client.batch_execute(
+            "ALTER TABLE sessions ADD COLUMN IF NOT EXISTS screen_width INTEGER;
+             ALTER TABLE sessions ADD COLUMN IF NOT EXISTS screen_height INTEGER;
+             ALTER TABLE sessions ADD COLUMN IF NOT EXISTS scale_factor DOUBLE PRECISION;
+             ALTER TABLE sessions ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'completed';
+             ALTER TABLE sessions ADD COLUMN IF NOT EXISTS contains_redacted BOOLEAN NOT NULL DEFAULT FALSE;",
+        ).await.map_err(|e| AppError::Database(e.into()))?;
+        
         client.execute(
             "CREATE TABLE IF NOT EXISTS events (
                 id BIGSERIAL PRIMARY KEY,
@@ -67,15 +91,20 @@ impl SessionRepository for PostgresSessionRepository {
         Ok(())
     }
     
-    async fn create_session(&self, name: &str, description: Option<&str>) -> AppResult<i64> {
+    async fn create_session(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        status: SessionStatus,
+    ) -> AppResult<i64> {
         let client = self.pool.get().await
             .map_err(|e| AppError::Database(e.into()))?;
         let created_at = Utc::now();
         
         let row = client.query_one(
-            "INSERT INTO sessions (name, description, created_at) 
-             VALUES ($1, $2, $3) RETURNING id",
-            &[&name, &description, &created_at],
+            "INSERT INTO sessions (name, description, created_at, status) 
+             VALUES ($1, $2, $3, $4) RETURNING id",
+            &[&name, &description, &created_at, &status.as_str()],
         ).await.map_err(|e| AppError::Database(e.into()))?;
         
         Ok(row.get(0))
@@ -86,7 +115,8 @@ impl SessionRepository for PostgresSessionRepository {
             .map_err(|e| AppError::Database(e.into()))?;
         
         let rows = client.query(
-            "SELECT id, name, description, created_at, event_count, time_cost 
+            "SELECT id, name, description, created_at, event_count, time_cost,
+                    screen_width, screen_height, scale_factor, status, contains_redacted
              FROM sessions WHERE id = $1",
             &[&session_id],
         ).await.map_err(|e| AppError::Database(e.into()))?;
@@ -99,30 +129,36 @@ impl SessionRepository for PostgresSessionRepository {
                 created_at: row.get(3),
                 event_count: row.get(4),
                 time_cost: row.get(5),
+                screen: Self::screen_from_row(row, 6),
+                status: SessionStatus::from_db(row.get(9)),
+                contains_redacted: row.get(10),
             }))
         } else {
             Ok(None)
         }
     }
     
-    async fn list_sessions(&self) -> AppResult<Vec<SessionInfo>> {
+    async fn list_sessions(&self) -> AppResult<Vec<Session>> {
         let client = self.pool.get().await
             .map_err(|e| AppError::Database(e.into()))?;
         
         let rows = client.query(
-            "SELECT id, name, description, 
-                    to_char(created_at, 'YYYY-MM-DD\"T\"HH24:MI:SS\"Z\"'), 
-                    event_count 
+            "SELECT id, name, description, created_at, event_count, time_cost,
+                    screen_width, screen_height, scale_factor, status, contains_redacted
              FROM sessions ORDER BY created_at DESC",
             &[],
         ).await.map_err(|e| AppError::Database(e.into()))?;
         
-        let sessions = rows.iter().map(|row| SessionInfo {
+        let sessions = rows.iter().map(|row| Session {
             id: row.get(0),
             name: row.get(1),
             description: row.get(2),
             created_at: row.get(3),
             event_count: row.get(4),
+            time_cost: row.get(5),
+            screen: Self::screen_from_row(row, 6),
+            status: SessionStatus::from_db(row.get(9)),
+            contains_redacted: row.get(10),
         }).collect();
         
         Ok(sessions)
@@ -155,6 +191,42 @@ impl SessionRepository for PostgresSessionRepository {
         Ok(())
     }
     
+    async fn set_session_status(&self, session_id: i64, status: SessionStatus) -> AppResult<()> {
+        let client = self.pool.get().await
+            .map_err(|e| AppError::Database(e.into()))?;
+        
+        client.execute(
+            "UPDATE sessions SET status = $1 WHERE id = $2",
+            &[&status.as_str(), &session_id],
+        ).await.map_err(|e| AppError::Database(e.into()))?;
+        
+        Ok(())
+    }
+    
+    async fn mark_session_redacted(&self, session_id: i64) -> AppResult<()> {
+        let client = self.pool.get().await
+            .map_err(|e| AppError::Database(e.into()))?;
+        
+        client.execute(
+            "UPDATE sessions SET contains_redacted = TRUE WHERE id = $1",
+            &[&session_id],
+        ).await.map_err(|e| AppError::Database(e.into()))?;
+        
+        Ok(())
+    }
+    
+    async fn set_session_screen(&self, session_id: i64, screen: &ScreenGeometry) -> AppResult<()> {
+        let client = self.pool.get().await
+            .map_err(|e| AppError::Database(e.into()))?;
+        
+        client.execute(
+            "UPDATE sessions SET screen_width = $1, screen_height = $2, scale_factor = $3 WHERE id = $4",
+            &[&(screen.width as i32), &(screen.height as i32), &screen.scale_factor, &session_id],
+        ).await.map_err(|e| AppError::Database(e.into()))?;
+        
+        Ok(())
+    }
+    
     async fn save_events(&self, session_id: i64, events: &[EventRecord]) -> AppResult<()> {
         let mut client = self.pool.get().await
             .map_err(|e| AppError::Database(e.into()))?;
@@ -173,7 +245,7 @@ impl SessionRepository for PostgresSessionRepository {
         }
         
         tx.execute(
-            "UPDATE sessions SET event_count = $1 WHERE id = $2",
+            "UPDATE sessions SET event_count = event_count + $1 WHERE id = $2",
             &[&(events.len() as i64), &session_id],
         ).await.map_err(|e| AppError::Database(e.into()))?;
         
@@ -181,6 +253,29 @@ impl SessionRepository for PostgresSessionRepository {
         Ok(())
     }
     
+    async fn delete_pointer_events_after(&self, session_id: i64, timestamp_ms: u128) -> AppResult<usize> {
+        let mut client = self.pool.get().await
+            .map_err(|e| AppError::Database(e.into()))?;
+        let tx = client.transaction().await
+            .map_err(|e| AppError::Database(e.into()))?;
+        
+        let deleted = tx.execute(
+            "DELETE FROM events WHERE session_id = $1 AND timestamp_ms > $2
+             AND action_type IN ('MouseMove', 'Wheel')",
+            &[&session_id, &(timestamp_ms as i64)],
+        ).await.map_err(|e| AppError::Database(e.into()))?;
+        
+        tx.execute(
+            "UPDATE sessions SET event_count = event_count - $1,
+                time_cost = COALESCE((SELECT MAX(timestamp_ms) FROM events WHERE session_id = $2), 0) / 1000.0
+             WHERE id = $2",
+            &[&(deleted as i64), &session_id],
+        ).await.map_err(|e| AppError::Database(e.into()))?;
+        
+        tx.commit().await.map_err(|e| AppError::Database(e.into()))?;
+        Ok(deleted as usize)
+    }
+    
     async fn load_events(&self, session_id: i64) -> AppResult<Vec<EventRecord>> {
         let client = self.pool.get().await
             .map_err(|e| AppError::Database(e.into()))?;
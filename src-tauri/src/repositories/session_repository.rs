@@ -1,4 +1,4 @@
-use crate::models::{EventRecord, Session};
+use crate::models::{EventRecord, ScreenGeometry, Session, SessionStatus};
 use crate::error::AppResult;
 use async_trait::async_trait;
 
@@ -8,7 +8,12 @@ pub trait SessionRepository: Send + Sync {
     async fn init(&self) -> AppResult<()>;
     
     /// 创建会话
-    async fn create_session(&self, name: &str, description: Option<&str>) -> AppResult<i64>;
+    async fn create_session(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        status: SessionStatus,
+    ) -> AppResult<i64>;
     
     /// 获取会话详情
     async fn get_session(&self, session_id: i64) -> AppResult<Option<Session>>;
@@ -27,9 +32,21 @@ pub trait SessionRepository: Send + Sync {
     /// 删除会话
     async fn delete_session(&self, session_id: i64) -> AppResult<()>;
     
+    /// 更新会话状态
+    async fn set_session_status(&self, session_id: i64, status: SessionStatus) -> AppResult<()>;
+    
+    /// 标记会话包含隐私模式下录制（已脱敏）的输入
+    async fn mark_session_redacted(&self, session_id: i64) -> AppResult<()>;
+    
+    /// 记录会话录制时的屏幕信息
+    async fn set_session_screen(&self, session_id: i64, screen: &ScreenGeometry) -> AppResult<()>;
+    
     /// 保存事件记录
     async fn save_events(&self, session_id: i64, events: &[EventRecord]) -> AppResult<()>;
     
     /// 加载事件记录
     async fn load_events(&self, session_id: i64) -> AppResult<Vec<EventRecord>>;
+    
+    /// 删除时间戳晚于 `timestamp_ms` 的鼠标移动和滚轮事件，返回删除数量
+    async fn delete_pointer_events_after(&self, session_id: i64, timestamp_ms: u128) -> AppResult<usize>;
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use super::SessionRepository;
-use crate::models::{EventRecord, Session, event};
+use crate::models::{EventRecord, ScreenGeometry, Session, SessionStatus, event};
 use crate::error::{AppError, AppResult};
 use async_trait::async_trait;
 use chrono::Utc;
@@ -22,6 +22,36 @@ impl SqliteSessionRepository {
             conn: Arc::new(Mutex::new(conn)),
         })
     }
+    
+    /// Add a column to an existing table unless it is already there (schema upgrade for old databases).
+    fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> AppResult<()> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .any(|name| name == column);
+        
+        if !exists {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+        }
+        Ok(())
+    }
+    
+    fn screen_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<Option<ScreenGeometry>> {
+        let width: Option<i64> = row.get(first)?;
+        let height: Option<i64> = row.get(first + 1)?;
+        let scale_factor: Option<f64> = row.get(first + 2)?;
+        
+        Ok(match (width, height) {
+            (Some(width), Some(height)) => Some(ScreenGeometry {
+                width: width as u32,
+                height: height as u32,
+                scale_factor: scale_factor.unwrap_or(1.0),
+            }),
+            _ => None,
+        })
+    }
 }
 
 #[async_trait]
@@ -36,11 +66,23 @@ impl SessionRepository for SqliteSessionRepository {
                 description TEXT,
                 created_at TEXT NOT NULL,
                 event_count INTEGER DEFAULT 0,
-                time_cost float64 DEFAULT 0.0
+                time_cost float64 DEFAULT 0.0,
+                screen_width INTEGER,
+                screen_height INTEGER,
+                scale_factor REAL,
+                status TEXT NOT NULL DEFAULT 'completed',
+                contains_redacted INTEGER NOT NULL DEFAULT 0
             )",
             [],
         )?;
         
+        // 旧数据库升级
+        Self::add_column_if_missing(&conn, "sessions", "screen_width", "INTEGER")?;
+        Self::add_column_if_missing(&conn, "sessions", "screen_height", "INTEGER")?;
+        Self::add_column_if_missing(&conn, "sessions", "scale_factor", "REAL")?;
+        Self::add_column_if_missing(&conn, "sessions", "status", "TEXT NOT NULL DEFAULT 'completed'")?;
+        Self::add_column_if_missing(&conn, "sessions", "contains_redacted", "INTEGER NOT NULL DEFAULT 0")?;
+        
         conn.execute(
             "CREATE TABLE IF NOT EXISTS events (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -67,13 +109,18 @@ impl SessionRepository for SqliteSessionRepository {
         Ok(())
     }
     
-    async fn create_session(&self, name: &str, description: Option<&str>) -> AppResult<i64> {
+    async fn create_session(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        status: SessionStatus,
+    ) -> AppResult<i64> {
         let conn = self.conn.lock().unwrap();
         let created_at = Utc::now().to_rfc3339();
         
         conn.execute(
-            "INSERT INTO sessions (name, description, created_at) VALUES (?1, ?2, ?3)",
-            params![name, description, created_at],
+            "INSERT INTO sessions (name, description, created_at, status) VALUES (?1, ?2, ?3, ?4)",
+            params![name, description, created_at, status.as_str()],
         )?;
         
         Ok(conn.last_insert_rowid())
@@ -82,7 +129,8 @@ impl SessionRepository for SqliteSessionRepository {
     async fn get_session(&self, session_id: i64) -> AppResult<Option<Session>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, name, description, created_at, event_count, time_cost 
+            "SELECT id, name, description, created_at, event_count, time_cost,
+                    screen_width, screen_height, scale_factor, status, contains_redacted
              FROM sessions WHERE id = ?1"
         )?;
         
@@ -101,6 +149,9 @@ impl SessionRepository for SqliteSessionRepository {
                 created_at,
                 event_count: row.get(4)?,
                 time_cost: row.get(5)?,
+                screen: Self::screen_from_row(row, 6)?,
+                status: SessionStatus::from_db(&row.get::<_, String>(9)?),
+                contains_redacted: row.get(10)?,
             }))
         } else {
             Ok(None)
@@ -110,7 +161,8 @@ impl SessionRepository for SqliteSessionRepository {
     async fn list_sessions(&self) -> AppResult<Vec<Session>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, name, description, created_at, event_count, time_cost 
+            "SELECT id, name, description, created_at, event_count, time_cost,
+                    screen_width, screen_height, scale_factor, status, contains_redacted
              FROM sessions ORDER BY created_at DESC"
         )?;
         
@@ -127,6 +179,9 @@ impl SessionRepository for SqliteSessionRepository {
                 created_at,
                 event_count: row.get(4)?,
                 time_cost: row.get(5)?,
+                screen: Self::screen_from_row(row, 6)?,
+                status: SessionStatus::from_db(&row.get::<_, String>(9)?),
+                contains_redacted: row.get(10)?,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
@@ -154,6 +209,33 @@ impl SessionRepository for SqliteSessionRepository {
         Ok(())
     }
     
+    async fn set_session_status(&self, session_id: i64, status: SessionStatus) -> AppResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sessions SET status = ?1 WHERE id = ?2",
+            params![status.as_str(), session_id],
+        )?;
+        Ok(())
+    }
+    
+    async fn mark_session_redacted(&self, session_id: i64) -> AppResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sessions SET contains_redacted = 1 WHERE id = ?1",
+            [session_id],
+        )?;
+        Ok(())
+    }
+    
+    async fn set_session_screen(&self, session_id: i64, screen: &ScreenGeometry) -> AppResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sessions SET screen_width = ?1, screen_height = ?2, scale_factor = ?3 WHERE id = ?4",
+            params![screen.width as i64, screen.height as i64, screen.scale_factor, session_id],
+        )?;
+        Ok(())
+    }
+    
     async fn save_events(&self, session_id: i64, events: &[EventRecord]) -> AppResult<()> {
         let conn = self.conn.lock().unwrap();
         let tx = conn.unchecked_transaction()?;
@@ -195,6 +277,26 @@ impl SessionRepository for SqliteSessionRepository {
         Ok(())
     }
     
+    async fn delete_pointer_events_after(&self, session_id: i64, timestamp_ms: u128) -> AppResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        
+        let deleted = tx.execute(
+            "DELETE FROM events WHERE session_id = ?1 AND timestamp_ms > ?2
+             AND action_type IN ('MouseMove', 'Wheel')",
+            params![session_id, timestamp_ms as i64],
+        )?;
+        tx.execute(
+            "UPDATE sessions SET event_count = event_count - ?1,
+                time_cost = COALESCE((SELECT MAX(timestamp_ms) FROM events WHERE session_id = ?2), 0) / 1000.0
+             WHERE id = ?2",
+            params![deleted as i64, session_id],
+        )?;
+        
+        tx.commit()?;
+        Ok(deleted)
+    }
+    
     async fn load_events(&self, session_id: i64) -> AppResult<Vec<EventRecord>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
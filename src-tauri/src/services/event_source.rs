@@ -0,0 +1,64 @@
+use crate::error::AppResult;
+use crate::services::{InputHub, Subscription};
+use rdev::Event;
+use std::time::SystemTime;
+
+pub type EventCallback = Box<dyn FnMut(Event) + Send>;
+
+/// Origin of raw input events for the recorder.
+pub trait EventSource: Send {
+    /// Start delivering events to `callback`; delivery ends when the returned subscription
+    /// is dropped. `origin` is the moment the recording started; sources that synthesize
+    /// events stamp them relative to it.
+    fn listen(self: Box<Self>, origin: SystemTime, callback: EventCallback) -> AppResult<Subscription>;
+}
+
+/// Global desktop input, received through the shared `InputHub`.
+pub struct RdevEventSource;
+
+impl EventSource for RdevEventSource {
+    fn listen(self: Box<Self>, _origin: SystemTime, callback: EventCallback) -> AppResult<Subscription> {
+        InputHub::subscribe(callback)
+    }
+}
+
+/// A fixed list of events, each stamped with an offset from the recording origin.
+/// Events are delivered back to back from `listen` itself, which lets the recorder run headless.
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedEventSource {
+    events: Vec<(u64, rdev::EventType, Option<String>)>,
+}
+
+#[cfg(test)]
+impl ScriptedEventSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an event `offset_ms` after the recording origin.
+    pub fn event(mut self, offset_ms: u64, event_type: rdev::EventType) -> Self {
+        self.events.push((offset_ms, event_type, None));
+        self
+    }
+
+    /// Append a key event that produced `name`, the way rdev reports typed characters.
+    pub fn typed(mut self, offset_ms: u64, event_type: rdev::EventType, name: &str) -> Self {
+        self.events.push((offset_ms, event_type, Some(name.to_string())));
+        self
+    }
+}
+
+#[cfg(test)]
+impl EventSource for ScriptedEventSource {
+    fn listen(self: Box<Self>, origin: SystemTime, mut callback: EventCallback) -> AppResult<Subscription> {
+        for (offset_ms, event_type, name) in self.events {
+            callback(Event {
+                time: origin + std::time::Duration::from_millis(offset_ms),
+                name,
+                event_type,
+            });
+        }
+        Ok(Subscription::none())
+    }
+}
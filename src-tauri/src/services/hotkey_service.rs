@@ -0,0 +1,92 @@
+use crate::error::AppResult;
+use crate::models::{Hotkey, HotkeyAction, Key};
+use crate::services::{InputHub, PlaybackControl, Subscription};
+use lazy_static::lazy_static;
+use rdev::EventType;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long an injected key event may take to come back through the global listener.
+const ECHO_TIMEOUT: Duration = Duration::from_millis(500);
+
+lazy_static! {
+    /// Key events the player has just injected. The listener swallows their echo so
+    /// replayed keystrokes can never trigger a hotkey.
+    static ref INJECTED_KEYS: Mutex<Vec<(Key, bool, Instant)>> = Mutex::new(Vec::new());
+    static ref SUBSCRIPTION: Mutex<Option<Subscription>> = Mutex::new(None);
+}
+
+pub struct HotkeyService;
+
+impl HotkeyService {
+    /// Subscribe the hotkey matcher to the input hub. It calls `handler` on the listener
+    /// thread whenever one of the `bindings` is pressed; starting again replaces the previous
+    /// bindings.
+    pub fn start<F>(bindings: Vec<(Hotkey, HotkeyAction)>, handler: F) -> AppResult<()>
+    where
+        F: Fn(HotkeyAction) + Send + 'static,
+    {
+        let mut held: HashSet<Key> = HashSet::new();
+
+        let callback = move |event: rdev::Event| {
+            let (key, pressed) = match event.event_type {
+                EventType::KeyPress(key) => (key, true),
+                EventType::KeyRelease(key) => (key, false),
+                _ => return,
+            };
+            let key = Key::from_rdev(key);
+            if Self::take_injected(key, pressed) {
+                return;
+            }
+
+            if pressed {
+                // Auto-repeat of a held key must not toggle again
+                if !held.contains(&key) {
+                    let hit = bindings.iter().find(|(hotkey, _)| hotkey.matches(key, &held));
+                    if let Some((_, action)) = hit {
+                        handler(*action);
+                    }
+                }
+                held.insert(key);
+            } else {
+                held.remove(&key);
+            }
+        };
+
+        let subscription = InputHub::subscribe(Box::new(callback))?;
+        let previous = SUBSCRIPTION.lock().unwrap().replace(subscription);
+        drop(previous);
+        Ok(())
+    }
+
+    /// Announce a key event the player is about to inject, so its echo is ignored.
+    pub fn note_injected(key: Key, pressed: bool) {
+        let mut injected = INJECTED_KEYS.lock().unwrap();
+        let now = Instant::now();
+        injected.retain(|(_, _, at)| now.duration_since(*at) < ECHO_TIMEOUT);
+        injected.push((key, pressed, now));
+    }
+
+    fn take_injected(key: Key, pressed: bool) -> bool {
+        let mut injected = INJECTED_KEYS.lock().unwrap();
+        let now = Instant::now();
+        injected.retain(|(_, _, at)| now.duration_since(*at) < ECHO_TIMEOUT);
+        match injected.iter().position(|(k, p, _)| *k == key && *p == pressed) {
+            Some(pos) => {
+                injected.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop `playback` if it is still running.
+    pub fn abort_playback(playback: &Mutex<Option<Arc<PlaybackControl>>>) {
+        if let Some(control) = playback.lock().unwrap().as_ref() {
+            if control.is_active() && control.stop().is_ok() {
+                println!("Panic hotkey pressed, stopping playback");
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::error::{AppError, AppResult};
+use crate::services::event_source::EventCallback;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+lazy_static! {
+    static ref SUBSCRIBERS: Mutex<Vec<(u64, Arc<Mutex<EventCallback>>)>> = Mutex::new(Vec::new());
+    /// Why the listener stopped, once it has
+    static ref FAILURE: Mutex<Option<String>> = Mutex::new(None);
+}
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The one process-wide `rdev::listen` loop. rdev offers no way to end a listener, so a
+/// single one is started on first use and every consumer (recorders, hotkeys) subscribes
+/// to it instead of spawning its own.
+pub struct InputHub;
+
+impl InputHub {
+    /// Start the listener thread; later calls are no-ops.
+    pub fn start() {
+        if STARTED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        std::thread::spawn(|| {
+            let dispatch = |event: rdev::Event| {
+                // Call outside the list lock so callbacks may subscribe or unsubscribe
+                let callbacks: Vec<_> = SUBSCRIBERS
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(_, callback)| callback.clone())
+                    .collect();
+                for callback in callbacks {
+                    (callback.lock().unwrap())(event.clone());
+                }
+            };
+
+            if let Err(err) = rdev::listen(dispatch) {
+                eprintln!("Input listener failed: {:?}", err);
+                *FAILURE.lock().unwrap() = Some(format!("{:?}", err));
+            }
+        });
+    }
+
+    /// Deliver every input event to `callback` until the returned subscription is dropped.
+    /// Callbacks run on the listener thread, one after another.
+    pub fn subscribe(callback: EventCallback) -> AppResult<Subscription> {
+        Self::start();
+        if let Some(err) = FAILURE.lock().unwrap().as_ref() {
+            return Err(AppError::RecordingError(format!("Listening failed: {}", err)));
+        }
+
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        SUBSCRIBERS.lock().unwrap().push((id, Arc::new(Mutex::new(callback))));
+        Ok(Subscription { id: Some(id) })
+    }
+
+    /// Stop delivering events to subscriber `id`. Doesn't wait: a dispatch already under way
+    /// may still hand it the current event.
+    pub fn unsubscribe(id: u64) {
+        SUBSCRIBERS.lock().unwrap().retain(|(other, _)| *other != id);
+    }
+}
+
+/// Keeps a hub subscription alive; dropping it unsubscribes.
+#[derive(Debug)]
+pub struct Subscription {
+    id: Option<u64>,
+}
+
+impl Subscription {
+    /// A subscription to nothing, for sources that don't go through the hub.
+    #[cfg(test)]
+    pub fn none() -> Self {
+        Self { id: None }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            InputHub::unsubscribe(id);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::error::{AppError, AppResult};
+use crate::models::{Action, EnigoKey, EventRecord, Key};
+use crate::services::HotkeyService;
+use enigo::{Axis, Button, Coordinate, Direction, Enigo, InputResult, Keyboard, Mouse, Settings};
+use std::time::{Duration, Instant};
+
+/// Destination of replayed actions. The player drives timing through the sink's own
+/// clock, so an implementation may run in real time or on a virtual timeline.
+pub trait InputSink {
+    /// Time elapsed on this sink's clock since it was created.
+    fn now(&self) -> Duration;
+
+    /// Let `duration` pass on this sink's clock.
+    fn wait(&mut self, duration: Duration);
+
+    /// Inject a single action.
+    fn inject(&mut self, action: &Action) -> AppResult<()>;
+
+    /// Size of the screen actions are injected into, if known.
+    fn screen_size(&self) -> Option<(u32, u32)>;
+}
+
+/// Injects actions into the real desktop session through enigo.
+/// Keys and mouse buttons still held when the sink is dropped (e.g. playback stopped or
+/// failed mid-hold) are released.
+pub struct EnigoSink {
+    enigo: Enigo,
+    started: Instant,
+    held_keys: Vec<Key>,
+    held_buttons: Vec<Button>,
+}
+
+impl EnigoSink {
+    pub fn new() -> AppResult<Self> {
+        let enigo = Enigo::new(&Settings::default())
+            .map_err(|e| AppError::PlaybackError(format!("Failed to initialize input: {}", e)))?;
+        Ok(Self {
+            enigo,
+            started: Instant::now(),
+            held_keys: Vec::new(),
+            held_buttons: Vec::new(),
+        })
+    }
+
+    fn key(enigo: &mut Enigo, key: Key, direction: Direction) -> InputResult<()> {
+        let Some(k) = key.to_enigo() else {
+            eprintln!("Skipping key that cannot be replayed: {}", key);
+            return Ok(());
+        };
+
+        if direction != Direction::Release {
+            HotkeyService::note_injected(key, true);
+        }
+        if direction != Direction::Press {
+            HotkeyService::note_injected(key, false);
+        }
+        match k {
+            EnigoKey::Key(k) => enigo.key(k, direction),
+            EnigoKey::Raw(code) => enigo.raw(code, direction),
+        }
+    }
+}
+
+impl Drop for EnigoSink {
+    fn drop(&mut self) {
+        for key in std::mem::take(&mut self.held_keys) {
+            let _ = Self::key(&mut self.enigo, key, Direction::Release);
+        }
+        for button in std::mem::take(&mut self.held_buttons) {
+            let _ = self.enigo.button(button, Direction::Release);
+        }
+    }
+}
+
+impl InputSink for EnigoSink {
+    fn now(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn wait(&mut self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    fn inject(&mut self, action: &Action) -> AppResult<()> {
+        let enigo = &mut self.enigo;
+        let result = match action {
+            Action::MouseMove { x, y } => enigo.move_mouse(*x, *y, Coordinate::Abs),
+            Action::MouseDown { button, x, y } => {
+                let button = button.to_enigo()?;
+                if !self.held_buttons.contains(&button) {
+                    self.held_buttons.push(button);
+                }
+                enigo
+                    .move_mouse(*x, *y, Coordinate::Abs)
+                    .and_then(|_| enigo.button(button, Direction::Press))
+            }
+            Action::MouseUp { button, x, y } => {
+                let button = button.to_enigo()?;
+                self.held_buttons.retain(|b| *b != button);
+                enigo
+                    .move_mouse(*x, *y, Coordinate::Abs)
+                    .and_then(|_| enigo.button(button, Direction::Release))
+            }
+            Action::Wheel { delta_x, delta_y, x, y } => {
+                // rdev reports "up" as a positive delta_y, enigo scrolls down for positive lengths
+                enigo.move_mouse(*x, *y, Coordinate::Abs).and_then(|_| {
+                    if *delta_y != 0 {
+                        enigo.scroll(-*delta_y, Axis::Vertical)?;
+                    }
+                    if *delta_x != 0 {
+                        enigo.scroll(*delta_x, Axis::Horizontal)?;
+                    }
+                    Ok(())
+                })
+            }
+            Action::KeyPress { key } => Self::key(enigo, *key, Direction::Click),
+            Action::KeyDown { key, .. } => {
+                if !self.held_keys.contains(key) {
+                    self.held_keys.push(*key);
+                }
+                Self::key(enigo, *key, Direction::Press)
+            }
+            Action::KeyUp { key } => {
+                self.held_keys.retain(|k| k != key);
+                Self::key(enigo, *key, Direction::Release)
+            }
+            Action::Text { text } => enigo.text(text),
+            // The player pauses for the user to enter it by hand
+            Action::Redacted => Ok(()),
+        };
+
+        result.map_err(|e| AppError::PlaybackError(format!("{} failed: {}", action.action_type(), e)))
+    }
+
+    fn screen_size(&self) -> Option<(u32, u32)> {
+        self.enigo
+            .main_display()
+            .ok()
+            .map(|(width, height)| (width as u32, height as u32))
+    }
+}
+
+/// Stores every injected action with its virtual timestamp instead of touching the desktop.
+/// Waiting only advances the virtual clock, so playback through this sink finishes instantly.
+#[derive(Debug, Default)]
+pub struct CapturingSink {
+    clock: Duration,
+    captured: Vec<EventRecord>,
+    screen: Option<(u32, u32)>,
+}
+
+impl CapturingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pretend to inject into a screen of the given size.
+    pub fn with_screen(mut self, width: u32, height: u32) -> Self {
+        self.screen = Some((width, height));
+        self
+    }
+
+    /// Captured actions, timestamped in milliseconds on the virtual clock.
+    pub fn into_events(self) -> Vec<EventRecord> {
+        self.captured
+    }
+}
+
+impl InputSink for CapturingSink {
+    fn now(&self) -> Duration {
+        self.clock
+    }
+
+    fn wait(&mut self, duration: Duration) {
+        self.clock += duration;
+    }
+
+    fn inject(&mut self, action: &Action) -> AppResult<()> {
+        self.captured
+            .push(EventRecord::new(self.clock.as_millis(), action.clone()));
+        Ok(())
+    }
+
+    fn screen_size(&self) -> Option<(u32, u32)> {
+        self.screen
+    }
+}
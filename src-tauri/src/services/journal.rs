@@ -0,0 +1,273 @@
+use crate::error::AppResult;
+use crate::models::{Action, EventRecord};
+use crate::repositories::SessionRepository;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// One line of a journal file. A batch is appended before it is saved and marked
+/// committed once the repository has it. Externally tagged, since serde can't read the
+/// `u128` timestamps back through an internally tagged enum.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum JournalEntry<'a> {
+    Batch {
+        seq: u64,
+        events: Cow<'a, [EventRecord]>,
+    },
+    Committed {
+        seq: u64,
+    },
+}
+
+/// What reading a journal file turned up.
+#[derive(Default)]
+struct Contents {
+    // Batches without a commit marker, by sequence number
+    uncommitted: BTreeMap<u64, Vec<EventRecord>>,
+    next_seq: u64,
+    // Some line couldn't be read, e.g. one torn by a crash mid-write
+    damaged: bool,
+}
+
+/// Append-only write-ahead log of a recording's event batches, one file per session.
+/// Batches that never got committed, after a crash or a database outage, are replayed into
+/// the repository on the next start.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    next_seq: u64,
+    // Sequence numbers appended but not committed
+    uncommitted: BTreeSet<u64>,
+    damaged: bool,
+}
+
+impl Journal {
+    /// Open the journal of `session_id` in `dir`, keeping anything already in it.
+    pub fn open(dir: &Path, session_id: i64) -> AppResult<Self> {
+        Ok(Self::load(dir, session_id)?.0)
+    }
+
+    /// Durably append a batch before it is saved. Returns its sequence number.
+    pub fn append(&mut self, events: &[EventRecord]) -> AppResult<u64> {
+        let seq = self.next_seq;
+        self.write(&JournalEntry::Batch {
+            seq,
+            events: Cow::Borrowed(events),
+        })?;
+        self.next_seq += 1;
+        self.uncommitted.insert(seq);
+        Ok(seq)
+    }
+
+    /// Durably mark batch `seq` as saved in the repository.
+    pub fn commit(&mut self, seq: u64) -> AppResult<()> {
+        self.write(&JournalEntry::Committed { seq })?;
+        self.uncommitted.remove(&seq);
+        Ok(())
+    }
+
+    /// Close the journal, removing the file when everything in it was committed. A journal
+    /// with unreadable lines is never removed; once committed it is renamed to
+    /// `*.jsonl.damaged` so it is kept for inspection but not replayed again.
+    pub fn finish(self) {
+        if !self.uncommitted.is_empty() {
+            return;
+        }
+        drop(self.file);
+        if self.damaged {
+            let kept = self.path.with_extension("jsonl.damaged");
+            match std::fs::rename(&self.path, &kept) {
+                Ok(()) => eprintln!("Kept damaged journal as {:?}", kept),
+                Err(e) => eprintln!("Failed to set aside damaged journal {:?}: {}", self.path, e),
+            }
+        } else if let Err(e) = std::fs::remove_file(&self.path) {
+            eprintln!("Failed to remove journal {:?}: {}", self.path, e);
+        }
+    }
+
+    /// Save every uncommitted batch found in `dir` into the repository. Batches the
+    /// repository already has, because the crash came between saving and committing, are
+    /// only marked committed. Journals that replay completely are finished; the others are
+    /// kept for the next attempt. Returns how many events were recovered.
+    pub async fn replay(dir: &Path, repository: &dyn SessionRepository) -> AppResult<usize> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut recovered = 0;
+        for entry in entries {
+            let path = entry?.path();
+            let Some(session_id) = Self::session_id(&path) else {
+                continue;
+            };
+
+            let (mut journal, pending) = Self::load(dir, session_id)?;
+            if !pending.is_empty() {
+                let saved = match repository.load_events(session_id).await {
+                    Ok(saved) => saved,
+                    Err(e) => {
+                        eprintln!("Failed to replay journal of session {}: {}", session_id, e);
+                        continue;
+                    }
+                };
+                let mut saved_at: BTreeMap<u128, Vec<Action>> = BTreeMap::new();
+                for record in saved {
+                    saved_at.entry(record.timestamp_ms).or_default().push(record.action);
+                }
+
+                for (seq, events) in pending {
+                    // Batches are saved in one transaction, so they are either all there or not at all
+                    let already_saved = events.iter().all(|record| {
+                        saved_at
+                            .get(&record.timestamp_ms)
+                            .is_some_and(|actions| actions.contains(&record.action))
+                    });
+                    if !already_saved {
+                        if let Err(e) = repository.save_events(session_id, &events).await {
+                            eprintln!("Failed to replay journal of session {}: {}", session_id, e);
+                            break;
+                        }
+                        recovered += events.len();
+                    }
+                    journal.commit(seq)?;
+                }
+            }
+            journal.finish();
+        }
+        Ok(recovered)
+    }
+
+    /// Open the journal file and return it along with its uncommitted batches, in order.
+    fn load(dir: &Path, session_id: i64) -> AppResult<(Self, BTreeMap<u64, Vec<EventRecord>>)> {
+        std::fs::create_dir_all(dir)?;
+        let path = Self::path(dir, session_id);
+        let contents = Self::read(&path)?;
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        let mut journal = Self {
+            path,
+            file,
+            next_seq: contents.next_seq,
+            uncommitted: contents.uncommitted.keys().copied().collect(),
+            damaged: contents.damaged,
+        };
+        // Start on a fresh line after a torn one
+        let bytes = std::fs::read(&journal.path)?;
+        if bytes.last().is_some_and(|byte| *byte != b'\n') {
+            journal.file.write_all(b"\n")?;
+        }
+        Ok((journal, contents.uncommitted))
+    }
+
+    fn path(dir: &Path, session_id: i64) -> PathBuf {
+        dir.join(format!("session-{}.jsonl", session_id))
+    }
+
+    fn session_id(path: &Path) -> Option<i64> {
+        let name = path.file_name()?.to_str()?;
+        name.strip_prefix("session-")?.strip_suffix(".jsonl")?.parse().ok()
+    }
+
+    fn write(&mut self, entry: &JournalEntry) -> AppResult<()> {
+        writeln!(self.file, "{}", serde_json::to_string(entry)?)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Read the journal at `path`. Unreadable lines, such as a torn last line left by a
+    /// crash mid-write, are skipped and flag the journal as damaged.
+    fn read(path: &Path) -> AppResult<Contents> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Contents::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut contents = Contents::default();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<JournalEntry>(&line) {
+                Ok(JournalEntry::Batch { seq, events }) => {
+                    contents.uncommitted.insert(seq, events.into_owned());
+                    contents.next_seq = contents.next_seq.max(seq + 1);
+                }
+                Ok(JournalEntry::Committed { seq }) => {
+                    contents.uncommitted.remove(&seq);
+                }
+                Err(e) => {
+                    eprintln!("Skipping unreadable journal line in {:?}: {}", path, e);
+                    contents.damaged = true;
+                }
+            }
+        }
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MouseButton, SessionStatus};
+    use crate::repositories::SqliteSessionRepository;
+
+    fn batch(start: u128) -> Vec<EventRecord> {
+        vec![
+            EventRecord::new(start, Action::MouseMove { x: 1, y: 2 }),
+            EventRecord::new(start + 5, Action::MouseDown { button: MouseButton::Left, x: 1, y: 2 }),
+        ]
+    }
+
+    #[tokio::test]
+    async fn replays_uncommitted_batches_once() {
+        let dir = std::env::temp_dir().join(format!("microplatter-journal-{}", std::process::id()));
+        let repository = SqliteSessionRepository::new(":memory:".to_string()).unwrap();
+        repository.init().await.unwrap();
+        let session_id = repository
+            .create_session("journal", None, SessionStatus::Recording)
+            .await
+            .unwrap();
+
+        let mut journal = Journal::open(&dir, session_id).unwrap();
+        // Saved and committed
+        let seq = journal.append(&batch(0)).unwrap();
+        repository.save_events(session_id, &batch(0)).await.unwrap();
+        journal.commit(seq).unwrap();
+        // Saved, but the crash came before the commit marker
+        journal.append(&batch(100)).unwrap();
+        repository.save_events(session_id, &batch(100)).await.unwrap();
+        // Never saved
+        journal.append(&batch(3_600_000)).unwrap();
+        drop(journal);
+
+        // A commit marker torn mid-write
+        let path = Journal::path(&dir, session_id);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"committed":{"se"#).unwrap();
+        drop(file);
+
+        assert_eq!(Journal::replay(&dir, &repository).await.unwrap(), 2);
+        let stored: Vec<u128> = repository
+            .load_events(session_id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|record| record.timestamp_ms)
+            .collect();
+        assert_eq!(stored, [0, 5, 100, 105, 3_600_000, 3_600_005]);
+
+        // Kept aside for inspection, and not replayed again
+        assert!(!path.exists());
+        assert!(path.with_extension("jsonl.damaged").exists());
+        assert_eq!(Journal::replay(&dir, &repository).await.unwrap(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
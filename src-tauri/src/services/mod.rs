@@ -1,5 +1,23 @@
 pub mod recorder_service;
+pub mod recorder_handle;
+pub mod record_queue;
 pub mod player_service;
+pub mod input_sink;
+pub mod event_source;
+pub mod playback_control;
+pub mod hotkey_service;
+pub mod input_hub;
+pub mod journal;
 
-pub use recorder_service::RecorderService;
-pub use player_service::PlayerService;
\ No newline at end of file
+pub use recorder_service::{RecorderService, RecordingPause};
+pub use recorder_handle::RecorderHandle;
+pub use record_queue::RecordQueue;
+pub use player_service::PlayerService;
+pub use input_sink::{CapturingSink, EnigoSink, InputSink};
+pub use event_source::{EventSource, RdevEventSource};
+#[cfg(test)]
+pub use event_source::ScriptedEventSource;
+pub use playback_control::PlaybackControl;
+pub use hotkey_service::HotkeyService;
+pub use input_hub::{InputHub, Subscription};
+pub use journal::Journal;
\ No newline at end of file
@@ -0,0 +1,178 @@
+use crate::error::{AppError, AppResult};
+use crate::models::{EventRecord, PlaybackOptions, PlaybackState, PlaybackStatus};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+/// Minimum gap between two `playback-progress` events.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shared handle between a running playback and the commands that steer it.
+/// The player reports progress and calls `checkpoint` between steps; commands
+/// flip the state and the player picks the change up at its next checkpoint.
+/// Every change is mirrored to the frontend as a `playback-*` event.
+pub struct PlaybackControl {
+    status: Mutex<PlaybackStatus>,
+    changed: Condvar,
+    // None when running headless (no frontend to notify)
+    app_handle: Option<tauri::AppHandle>,
+    last_progress: Mutex<Option<Instant>>,
+}
+
+impl PlaybackControl {
+    pub fn new(
+        session_id: i64,
+        records: &[EventRecord],
+        options: &PlaybackOptions,
+        app_handle: Option<tauri::AppHandle>,
+    ) -> Self {
+        let span_ms = match (records.first(), records.last()) {
+            (Some(first), Some(last)) => last.timestamp_ms.saturating_sub(first.timestamp_ms),
+            _ => 0,
+        };
+
+        Self {
+            status: Mutex::new(PlaybackStatus {
+                session_id,
+                state: PlaybackState::Running,
+                event_index: 0,
+                total_events: records.len(),
+                iteration: 1,
+                total_iterations: options.repeat.total(),
+                elapsed_ms: 0,
+                total_ms: options.scale(span_ms).as_millis() as u64,
+                error: None,
+            }),
+            changed: Condvar::new(),
+            app_handle,
+            last_progress: Mutex::new(None),
+        }
+    }
+
+    pub fn status(&self) -> PlaybackStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.status.lock().unwrap().state.is_active()
+    }
+
+    pub fn pause(&self) -> AppResult<()> {
+        let mut status = self.status.lock().unwrap();
+        match status.state {
+            PlaybackState::Running => {
+                status.state = PlaybackState::Paused;
+                self.emit("playback-progress", &status);
+                Ok(())
+            }
+            PlaybackState::Paused => Ok(()),
+            _ => Err(AppError::NotPlaying),
+        }
+    }
+
+    pub fn resume(&self) -> AppResult<()> {
+        let mut status = self.status.lock().unwrap();
+        match status.state {
+            PlaybackState::Paused => {
+                status.state = PlaybackState::Running;
+                self.changed.notify_all();
+                self.emit("playback-progress", &status);
+                Ok(())
+            }
+            PlaybackState::Running => Ok(()),
+            _ => Err(AppError::NotPlaying),
+        }
+    }
+
+    /// Ask the player to stop. Also wakes a paused player so it can exit.
+    pub fn stop(&self) -> AppResult<()> {
+        let mut status = self.status.lock().unwrap();
+        if !status.state.is_active() {
+            return Err(AppError::NotPlaying);
+        }
+        status.state = PlaybackState::Stopping;
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    /// Pause at the start of a redacted stretch and emit `playback-redacted`, so the frontend
+    /// can prompt the user to enter that input by hand and resume. Headless playback has
+    /// nobody to resume it and carries on.
+    pub fn pause_for_redacted(&self) {
+        if self.app_handle.is_none() {
+            return;
+        }
+        let mut status = self.status.lock().unwrap();
+        if status.state == PlaybackState::Running {
+            status.state = PlaybackState::Paused;
+            self.emit("playback-redacted", &status);
+        }
+    }
+
+    /// Called by the player between steps: blocks while paused and returns
+    /// `PlaybackStopped` once a stop has been requested.
+    pub fn checkpoint(&self) -> AppResult<()> {
+        let mut status = self.status.lock().unwrap();
+        while status.state == PlaybackState::Paused {
+            status = self.changed.wait(status).unwrap();
+        }
+        if status.state == PlaybackState::Stopping {
+            return Err(AppError::PlaybackStopped);
+        }
+        Ok(())
+    }
+
+    pub fn started(&self) {
+        self.emit("playback-started", &self.status.lock().unwrap());
+    }
+
+    /// Report that `event_index` events of the current iteration have been injected,
+    /// `elapsed_ms` into it. Progress events are throttled, the last event always goes out.
+    pub fn set_progress(&self, event_index: usize, elapsed_ms: u64) {
+        let mut status = self.status.lock().unwrap();
+        status.event_index = event_index;
+        status.elapsed_ms = elapsed_ms;
+
+        let mut last = self.last_progress.lock().unwrap();
+        let due = last.is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL);
+        if due || event_index == status.total_events {
+            *last = Some(Instant::now());
+            self.emit("playback-progress", &status);
+        }
+    }
+
+    /// Enter the given (1-based) iteration; progress restarts from the first event.
+    pub fn set_iteration(&self, iteration: u32) {
+        let mut status = self.status.lock().unwrap();
+        status.iteration = iteration;
+        status.event_index = 0;
+        status.elapsed_ms = 0;
+        self.emit("playback-progress", &status);
+    }
+
+    /// Record how the playback ended.
+    pub fn finish(&self, result: &AppResult<()>) {
+        let mut status = self.status.lock().unwrap();
+        status.state = match result {
+            Ok(()) => PlaybackState::Finished,
+            Err(AppError::PlaybackStopped) => PlaybackState::Stopped,
+            Err(e) => {
+                status.error = Some(e.to_string());
+                PlaybackState::Failed
+            }
+        };
+        self.changed.notify_all();
+
+        let event = match status.state {
+            PlaybackState::Failed => "playback-failed",
+            _ => "playback-finished",
+        };
+        self.emit(event, &status);
+    }
+
+    fn emit(&self, event: &str, status: &PlaybackStatus) {
+        if let Some(app_handle) = self.app_handle.as_ref() {
+            let _ = app_handle.emit(event, status.clone());
+        }
+    }
+}
@@ -1,22 +1,483 @@
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    Action, CoordinateMapping, DryRunReport, EventRecord, Key, MouseButton, PlaybackOptions,
+    PlaybackWarning, RepeatMode, ScreenGeometry,
+};
 use crate::repositories::SessionRepository;
-use crate::error::AppResult;
+use crate::services::{CapturingSink, EnigoSink, InputSink, PlaybackControl};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Longest single wait between checkpoints, so pause/stop take effect promptly
+/// even across long gaps in the recording.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_millis(50);
 
 pub struct PlayerService;
 
 impl PlayerService {
+    /// Load a session and start replaying it on a background thread.
+    /// Returns immediately with the control handle of the running playback.
     pub async fn play_session(
         session_id: i64,
         repository: &dyn SessionRepository,
+        options: PlaybackOptions,
+        app_handle: Option<tauri::AppHandle>,
+    ) -> AppResult<Arc<PlaybackControl>> {
+        options.validate()?;
+        let session = repository
+            .get_session(session_id)
+            .await?
+            .ok_or(AppError::SessionNotFound(session_id))?;
+        let mut records = repository.load_events(session_id).await?;
+
+        println!("Replaying {} events for session {}", records.len(), session_id);
+
+        let control = Arc::new(PlaybackControl::new(session_id, &records, &options, app_handle));
+        let control_bg = control.clone();
+
+        // enigo 注入和 sleep 都是阻塞调用，放到独立线程里执行
+        std::thread::spawn(move || {
+            let result = EnigoSink::new().and_then(|mut sink| {
+                Self::remap_records(&mut records, &options, session.screen, sink.screen_size())?;
+                Self::play_events(&records, &mut sink, &options, &control_bg)
+            });
+            if let Err(e) = &result {
+                eprintln!("Playback of session {} ended: {}", session_id, e);
+            }
+            control_bg.finish(&result);
+        });
+
+        Ok(control)
+    }
+
+    /// Replay a stored session into `sink` and hand the sink back once playback is done.
+    pub async fn play_session_into<S>(
+        session_id: i64,
+        repository: &dyn SessionRepository,
+        mut sink: S,
+        options: PlaybackOptions,
+    ) -> AppResult<S>
+    where
+        S: InputSink + Send + 'static,
+    {
+        options.validate()?;
+        let session = repository
+            .get_session(session_id)
+            .await?
+            .ok_or(AppError::SessionNotFound(session_id))?;
+        let mut records = repository.load_events(session_id).await?;
+        Self::remap_records(&mut records, &options, session.screen, sink.screen_size())?;
+        let control = PlaybackControl::new(session_id, &records, &options, None);
+
+        tokio::task::spawn_blocking(move || {
+            let result = Self::play_events(&records, &mut sink, &options, &control);
+            control.finish(&result);
+            result.map(|_| sink)
+        })
+        .await
+        .map_err(|e| AppError::PlaybackError(e.to_string()))?
+    }
+
+    /// Run a session through the full scheduling path into a `CapturingSink` and report
+    /// what playback would do, without injecting anything.
+    pub async fn dry_run(
+        session_id: i64,
+        repository: &dyn SessionRepository,
+        mut options: PlaybackOptions,
+    ) -> AppResult<DryRunReport> {
+        let mut warnings = Vec::new();
+        if options.repeat == RepeatMode::UntilCancelled {
+            options.repeat = RepeatMode::Once;
+            warnings.push(PlaybackWarning {
+                action_index: None,
+                message: "Repeating until cancelled is simulated as a single iteration".to_string(),
+            });
+        }
+
+        // Check against the screen we're on now, or the recorded one when it can't be queried
+        let session = repository
+            .get_session(session_id)
+            .await?
+            .ok_or(AppError::SessionNotFound(session_id))?;
+        let screen = ScreenGeometry::detect(None).or(session.screen);
+        let sink = match screen {
+            Some(screen) => CapturingSink::new().with_screen(screen.width, screen.height),
+            None => CapturingSink::new(),
+        };
+
+        let actions = Self::play_session_into(session_id, repository, sink, options)
+            .await?
+            .into_events();
+        warnings.extend(Self::check_actions(&actions, screen));
+
+        let mut counts = BTreeMap::new();
+        for record in &actions {
+            *counts.entry(record.action.action_type().to_string()).or_insert(0) += 1;
+        }
+
+        Ok(DryRunReport {
+            session_id,
+            total_duration_ms: actions.last().map(|r| r.timestamp_ms as u64).unwrap_or(0),
+            actions,
+            counts,
+            warnings,
+        })
+    }
+
+    /// Look for actions that are likely to misbehave when replayed.
+    fn check_actions(actions: &[EventRecord], screen: Option<ScreenGeometry>) -> Vec<PlaybackWarning> {
+        let mut warnings = Vec::new();
+        let mut pressed: Vec<MouseButton> = Vec::new();
+        let mut held_keys: Vec<Key> = Vec::new();
+
+        for (index, record) in actions.iter().enumerate() {
+            let mut warn = |message: String| {
+                warnings.push(PlaybackWarning {
+                    action_index: Some(index),
+                    message,
+                })
+            };
+
+            if let Action::MouseDown { button, .. } | Action::MouseUp { button, .. } = &record.action {
+                if let Err(e) = button.to_enigo() {
+                    warn(e.to_string());
+                }
+            }
+            match &record.action {
+                Action::MouseDown { button, .. } => pressed.push(button.clone()),
+                Action::MouseUp { button, .. } => match pressed.iter().position(|b| b == button) {
+                    Some(pos) => {
+                        pressed.remove(pos);
+                    }
+                    None => warn(format!("{:?} button released without being pressed", button)),
+                },
+                Action::KeyPress { key } | Action::KeyDown { key, .. } | Action::KeyUp { key } => {
+                    // Checked before mapping, which turns unknown keys into raw keycodes on some platforms
+                    if let Key::Unknown(_) = key {
+                        warn(format!("Unknown key '{}' may not replay as recorded", key))
+                    } else if key.to_enigo().is_none() {
+                        warn(format!("Key '{}' can't be replayed here and will be skipped", key))
+                    }
+                }
+                _ => {}
+            }
+            match &record.action {
+                Action::KeyDown { key, .. } if !held_keys.contains(key) => held_keys.push(*key),
+                Action::KeyUp { key } => match held_keys.iter().position(|k| k == key) {
+                    Some(pos) => {
+                        held_keys.remove(pos);
+                    }
+                    None => warn(format!("Key {} released without being pressed", key)),
+                },
+                _ => {}
+            }
+
+            if record.action == Action::Redacted
+                && (index == 0 || actions[index - 1].action != Action::Redacted)
+            {
+                warn("Redacted input; playback pauses here for it to be entered by hand".to_string());
+            }
+
+            if let (Some(screen), Some((x, y))) = (screen, record.action.position()) {
+                if x < 0 || y < 0 || x >= screen.width as i32 || y >= screen.height as i32 {
+                    warn(format!(
+                        "Position ({}, {}) is outside the {}x{} screen",
+                        x, y, screen.width, screen.height
+                    ));
+                }
+            }
+        }
+
+        for button in pressed {
+            warnings.push(PlaybackWarning {
+                action_index: None,
+                message: format!("{:?} button is still pressed when playback ends", button),
+            });
+        }
+        for key in held_keys {
+            warnings.push(PlaybackWarning {
+                action_index: None,
+                message: format!("Key {} is still held when playback ends", key),
+            });
+        }
+        warnings
+    }
+
+    /// Adapt recorded coordinates to the screen playback happens on, as selected by
+    /// `options.coordinate_mapping`.
+    pub fn remap_records(
+        records: &mut [EventRecord],
+        options: &PlaybackOptions,
+        recorded: Option<ScreenGeometry>,
+        current: Option<(u32, u32)>,
     ) -> AppResult<()> {
-        // Minimal playback stub: load events and log a summary.
-        let records = repository.load_events(session_id).await?;
+        let map: Box<dyn Fn(i32, i32) -> (i32, i32)> = match options.coordinate_mapping {
+            CoordinateMapping::None => return Ok(()),
+            CoordinateMapping::Offset { dx, dy } => Box::new(move |x, y| (x + dx, y + dy)),
+            CoordinateMapping::Scale => {
+                let recorded = recorded.ok_or_else(|| {
+                    AppError::PlaybackError(
+                        "Session has no recorded screen geometry to scale from".to_string(),
+                    )
+                })?;
+                let (width, height) = current.ok_or_else(|| {
+                    AppError::PlaybackError("Current screen size is unknown".to_string())
+                })?;
+                if recorded.width == 0 || recorded.height == 0 {
+                    return Err(AppError::PlaybackError(
+                        "Recorded screen geometry is empty".to_string(),
+                    ));
+                }
 
-        if records.is_empty() {
-            println!("No events in this session.");
-            return Ok(());
+                let sx = width as f64 / recorded.width as f64;
+                let sy = height as f64 / recorded.height as f64;
+                Box::new(move |x, y| {
+                    ((x as f64 * sx).round() as i32, (y as f64 * sy).round() as i32)
+                })
+            }
+        };
+
+        for record in records.iter_mut() {
+            record.action = record.action.map_position(&map);
+        }
+        Ok(())
+    }
+
+    /// Replay the records as many times as `options.repeat` asks for. Honors
+    /// pause/stop requests on `control` throughout, including between iterations.
+    pub fn play_events(
+        records: &[EventRecord],
+        sink: &mut dyn InputSink,
+        options: &PlaybackOptions,
+        control: &PlaybackControl,
+    ) -> AppResult<()> {
+        control.started();
+
+        let mut iteration = 1;
+        loop {
+            control.set_iteration(iteration);
+            Self::play_once(records, sink, options, control)?;
+
+            // An empty session would otherwise spin forever when looping until cancelled
+            if records.is_empty() || !options.repeat.has_next(iteration) {
+                return Ok(());
+            }
+
+            let mut origin = sink.now();
+            let delay = Duration::from_millis(options.iteration_delay_ms);
+            Self::wait_until(sink, control, &mut origin, delay)?;
+            iteration += 1;
+        }
+    }
+
+    /// Replay the records once, keeping the original gaps between timestamps
+    /// scaled by the playback speed.
+    fn play_once(
+        records: &[EventRecord],
+        sink: &mut dyn InputSink,
+        options: &PlaybackOptions,
+        control: &PlaybackControl,
+    ) -> AppResult<()> {
+        // Schedule against a fixed origin so per-event injection cost doesn't accumulate as drift.
+        let first_ts = records.first().map(|r| r.timestamp_ms).unwrap_or(0);
+        let mut origin = sink.now();
+        // Keys whose press was entered as text; their releases are skipped
+        let mut typed: Vec<Key> = Vec::new();
+
+        for (index, record) in records.iter().enumerate() {
+            let offset = options.scale(record.timestamp_ms.saturating_sub(first_ts));
+            Self::wait_until(sink, control, &mut origin, offset)?;
+
+            let redacted_start = record.action == Action::Redacted
+                && (index == 0 || records[index - 1].action != Action::Redacted);
+            if redacted_start {
+                control.pause_for_redacted();
+                // Blocks until resumed; the time paused doesn't count
+                Self::wait_until(sink, control, &mut origin, offset)?;
+            }
+
+            match &record.action {
+                // Plain ASCII keys keep their hold time, e.g. WASD movement
+                Action::KeyDown { key, text: Some(text) } if options.type_text && !text.is_ascii() => {
+                    if !typed.contains(key) {
+                        typed.push(*key);
+                    }
+                    sink.inject(&Action::Text { text: text.clone() })?;
+                }
+                Action::KeyUp { key } if typed.contains(key) => typed.retain(|k| k != key),
+                action => sink.inject(action)?,
+            }
+            control.set_progress(index + 1, offset.as_millis() as u64);
         }
 
-        println!("Loaded {} events for session {}. Playback is not implemented in this build.", records.len(), session_id);
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Wait until `offset` past `origin` on the sink's clock, checking `control` at least
+    /// every `CHECKPOINT_INTERVAL`. Time spent paused pushes `origin` back so it doesn't
+    /// count towards the schedule.
+    fn wait_until(
+        sink: &mut dyn InputSink,
+        control: &PlaybackControl,
+        origin: &mut Duration,
+        offset: Duration,
+    ) -> AppResult<()> {
+        loop {
+            let before = sink.now();
+            control.checkpoint()?;
+            *origin += sink.now() - before;
+
+            let target = *origin + offset;
+            let now = sink.now();
+            if target <= now {
+                return Ok(());
+            }
+            sink.wait((target - now).min(CHECKPOINT_INTERVAL));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SessionStatus;
+    use crate::repositories::SqliteSessionRepository;
+
+    async fn stored_session(events: &[EventRecord]) -> (SqliteSessionRepository, i64) {
+        let repository = SqliteSessionRepository::new(":memory:".to_string()).unwrap();
+        repository.init().await.unwrap();
+        let session_id = repository
+            .create_session("capture", None, SessionStatus::Completed)
+            .await
+            .unwrap();
+        repository.save_events(session_id, events).await.unwrap();
+        (repository, session_id)
+    }
+
+    async fn capture(
+        repository: &SqliteSessionRepository,
+        session_id: i64,
+        options: PlaybackOptions,
+    ) -> Vec<(u128, Action)> {
+        PlayerService::play_session_into(session_id, repository, CapturingSink::new(), options)
+            .await
+            .unwrap()
+            .into_events()
+            .into_iter()
+            .map(|record| (record.timestamp_ms, record.action))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn remaps_coordinates_to_the_playback_screen() {
+        let (repository, session_id) = stored_session(&[
+            EventRecord::new(0, Action::MouseMove { x: 100, y: 50 }),
+            EventRecord::new(10, Action::Wheel { delta_x: 0, delta_y: 1, x: 999, y: 499 }),
+            EventRecord::new(20, Action::KeyDown { key: Key::Escape, text: None }),
+        ])
+        .await;
+        let repository = &repository;
+        let into = |mapping| async move {
+            let options = PlaybackOptions {
+                coordinate_mapping: mapping,
+                ..PlaybackOptions::default()
+            };
+            let sink = CapturingSink::new().with_screen(2000, 1000);
+            PlayerService::play_session_into(session_id, repository, sink, options)
+                .await
+                .map(|sink| sink.into_events().into_iter().map(|r| r.action).collect::<Vec<_>>())
+        };
+
+        // Scaling needs the screen the session was recorded on
+        assert!(into(CoordinateMapping::Scale).await.is_err());
+
+        let offset = into(CoordinateMapping::Offset { dx: 10, dy: -5 }).await.unwrap();
+        assert_eq!(
+            offset,
+            [
+                Action::MouseMove { x: 110, y: 45 },
+                Action::Wheel { delta_x: 0, delta_y: 1, x: 1009, y: 494 },
+                Action::KeyDown { key: Key::Escape, text: None },
+            ]
+        );
+
+        let recorded = ScreenGeometry { width: 1000, height: 500, scale_factor: 1.0 };
+        repository.set_session_screen(session_id, &recorded).await.unwrap();
+        let scaled = into(CoordinateMapping::Scale).await.unwrap();
+        assert_eq!(
+            scaled,
+            [
+                Action::MouseMove { x: 200, y: 100 },
+                Action::Wheel { delta_x: 0, delta_y: 1, x: 1998, y: 998 },
+                Action::KeyDown { key: Key::Escape, text: None },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_stored_session_on_virtual_clock() {
+        let (repository, session_id) = stored_session(&[
+            EventRecord::new(1000, Action::MouseMove { x: 10, y: 20 }),
+            EventRecord::new(1100, Action::MouseDown { button: MouseButton::Left, x: 10, y: 20 }),
+            EventRecord::new(1350, Action::MouseUp { button: MouseButton::Left, x: 10, y: 20 }),
+            EventRecord::new(2000, Action::Text { text: "hi".to_string() }),
+        ])
+        .await;
+
+        let options = PlaybackOptions {
+            repeat: RepeatMode::Times { count: 2 },
+            iteration_delay_ms: 500,
+            ..PlaybackOptions::default()
+        };
+        let pass = |start: u128| {
+            vec![
+                (start, Action::MouseMove { x: 10, y: 20 }),
+                (start + 100, Action::MouseDown { button: MouseButton::Left, x: 10, y: 20 }),
+                (start + 350, Action::MouseUp { button: MouseButton::Left, x: 10, y: 20 }),
+                (start + 1000, Action::Text { text: "hi".to_string() }),
+            ]
+        };
+        assert_eq!(
+            capture(&repository, session_id, options).await,
+            [pass(0), pass(1500)].concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn enters_recorded_text_or_presses_keys() {
+        let typed = Some("é".to_string());
+        let w = Some("w".to_string());
+        let (repository, session_id) = stored_session(&[
+            EventRecord::new(0, Action::KeyDown { key: Key::KeyE, text: typed.clone() }),
+            EventRecord::new(400, Action::KeyUp { key: Key::KeyE }),
+            EventRecord::new(500, Action::KeyDown { key: Key::KeyW, text: w.clone() }),
+            EventRecord::new(1500, Action::KeyUp { key: Key::KeyW }),
+        ])
+        .await;
+
+        // ASCII keys stay held for as long as they were recorded
+        assert_eq!(
+            capture(&repository, session_id, PlaybackOptions::default()).await,
+            [
+                (0, Action::Text { text: "é".to_string() }),
+                (500, Action::KeyDown { key: Key::KeyW, text: w.clone() }),
+                (1500, Action::KeyUp { key: Key::KeyW }),
+            ]
+        );
+
+        let options = PlaybackOptions {
+            type_text: false,
+            ..PlaybackOptions::default()
+        };
+        assert_eq!(
+            capture(&repository, session_id, options).await,
+            [
+                (0, Action::KeyDown { key: Key::KeyE, text: typed }),
+                (400, Action::KeyUp { key: Key::KeyE }),
+                (500, Action::KeyDown { key: Key::KeyW, text: w }),
+                (1500, Action::KeyUp { key: Key::KeyW }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use crate::error::AppResult;
+use crate::models::{Action, EventRecord, OverflowPolicy, QueueStats};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Bounded queue between the recorder callback and the flusher thread. When it is full,
+/// the `OverflowPolicy` decides whether events are dropped or spilled to disk. Pushing never
+/// blocks, as it runs on the input listener thread.
+pub struct RecordQueue {
+    capacity: usize,
+    policy: OverflowPolicy,
+    spill_path: PathBuf,
+    inner: Mutex<QueueInner>,
+    // Signalled when records arrive or the queue closes
+    filled: Condvar,
+}
+
+struct QueueInner {
+    records: VecDeque<EventRecord>,
+    // Records written to the spill file and not read back yet; newer than everything in memory
+    spilled: usize,
+    spill: Option<File>,
+    // Presses dropped on overflow whose release hasn't arrived yet
+    dropped_presses: Vec<Action>,
+    persisted: usize,
+    dropped: usize,
+    closed: bool,
+    // Last time a record was offered or `touch` was called, for the idle auto-stop
+    active_at: Instant,
+}
+
+impl RecordQueue {
+    pub fn new(capacity: usize, policy: OverflowPolicy, spill_path: PathBuf) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            policy,
+            spill_path,
+            inner: Mutex::new(QueueInner {
+                records: VecDeque::new(),
+                spilled: 0,
+                spill: None,
+                dropped_presses: Vec::new(),
+                persisted: 0,
+                dropped: 0,
+                closed: false,
+                active_at: Instant::now(),
+            }),
+            filled: Condvar::new(),
+        }
+    }
+
+    /// Queue a record. Returns false once the queue is closed.
+    pub fn push(&self, record: EventRecord) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.closed {
+            return false;
+        }
+        inner.active_at = Instant::now();
+
+        // Keep spilling until the file is read back so records stay in order
+        let full = inner.records.len() >= self.capacity;
+        match self.policy {
+            OverflowPolicy::SpillToDisk if full || inner.spilled > 0 => {
+                if let Err(e) = self.spill(&mut inner, &record) {
+                    eprintln!("Failed to spill event to disk: {}", e);
+                    inner.dropped += 1;
+                }
+                self.filled.notify_one();
+                return true;
+            }
+            OverflowPolicy::DropOldestMoves => {
+                // The release of a dropped press goes with it, so playback never sees it unpaired
+                if let Some(index) = inner
+                    .dropped_presses
+                    .iter()
+                    .position(|press| releases(&record.action, press))
+                {
+                    inner.dropped_presses.swap_remove(index);
+                    inner.dropped += 1;
+                    return true;
+                }
+                if full {
+                    let oldest_move = inner.records.iter().position(|r| {
+                        matches!(r.action, Action::MouseMove { .. } | Action::Wheel { .. })
+                    });
+                    match oldest_move {
+                        Some(index) => {
+                            inner.records.remove(index);
+                            inner.dropped += 1;
+                        }
+                        // Releases go over capacity rather than leave a recorded press held
+                        None if matches!(
+                            record.action,
+                            Action::MouseUp { .. } | Action::KeyUp { .. }
+                        ) => {}
+                        // Clicks and keys already queued are never evicted, the new event goes instead
+                        None => {
+                            inner.dropped += 1;
+                            if matches!(
+                                record.action,
+                                Action::MouseDown { .. } | Action::KeyDown { .. }
+                            ) {
+                                inner.dropped_presses.push(record.action);
+                            }
+                            return true;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        inner.records.push_back(record);
+        self.filled.notify_one();
+        true
+    }
+
+    /// Take everything queued once `deadline` passes, the queue fills up or it is closed.
+    /// Spilled records are read back once the in-memory ones are gone. Returns the batch
+    /// and whether the queue is closed and fully drained.
+    pub fn take_batch(&self, deadline: Instant) -> (Vec<EventRecord>, bool) {
+        let mut inner = self.inner.lock().unwrap();
+        while inner.records.len() < self.capacity && inner.spilled == 0 && !inner.closed {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                break;
+            }
+            inner = self.filled.wait_timeout(inner, timeout).unwrap().0;
+        }
+
+        let batch: Vec<EventRecord> = if !inner.records.is_empty() {
+            inner.records.drain(..).collect()
+        } else if inner.spilled > 0 {
+            match self.read_spill(&mut inner) {
+                Ok(records) => records,
+                Err(e) => {
+                    eprintln!("Failed to read spilled events: {}", e);
+                    inner.dropped += inner.spilled;
+                    inner.spilled = 0;
+                    Vec::new()
+                }
+            }
+        } else {
+            Vec::new()
+        };
+
+        let done = inner.closed && inner.records.is_empty() && inner.spilled == 0;
+        (batch, done)
+    }
+
+    /// Count records the flusher has saved.
+    pub fn mark_persisted(&self, count: usize) {
+        self.inner.lock().unwrap().persisted += count;
+    }
+
+    /// Refuse further records and wake the flusher. Queued records are still handed out.
+    pub fn close(&self) {
+        self.inner.lock().unwrap().closed = true;
+        self.filled.notify_all();
+    }
+
+    /// Restart the idle clock without queueing anything, e.g. on input that isn't recorded.
+    pub fn touch(&self) {
+        self.inner.lock().unwrap().active_at = Instant::now();
+    }
+
+    /// How long since the last record was offered or `touch` was called.
+    pub fn idle_for(&self) -> Duration {
+        self.inner.lock().unwrap().active_at.elapsed()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.lock().unwrap().closed
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let inner = self.inner.lock().unwrap();
+        QueueStats {
+            queued: inner.records.len() + inner.spilled,
+            persisted: inner.persisted,
+            dropped: inner.dropped,
+        }
+    }
+
+    fn spill(&self, inner: &mut QueueInner, record: &EventRecord) -> AppResult<()> {
+        if inner.spill.is_none() {
+            let mut options = OpenOptions::new();
+            options.read(true).write(true).create(true).truncate(true);
+            // It holds recorded keys in plain text
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let file = options.open(&self.spill_path)?;
+            inner.spill = Some(file);
+        }
+        let file = inner.spill.as_mut().unwrap();
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        inner.spilled += 1;
+        Ok(())
+    }
+
+    /// Read back every spilled record and empty the file.
+    fn read_spill(&self, inner: &mut QueueInner) -> AppResult<Vec<EventRecord>> {
+        let Some(file) = inner.spill.as_mut() else {
+            return Ok(Vec::new());
+        };
+        file.seek(SeekFrom::Start(0))?;
+        let mut records = Vec::with_capacity(inner.spilled);
+        for line in BufReader::new(&*file).lines() {
+            records.push(serde_json::from_str(&line?)?);
+        }
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        inner.spilled = 0;
+        Ok(records)
+    }
+}
+
+/// Whether `release` lets go of the key or button held down by `press`.
+fn releases(release: &Action, press: &Action) -> bool {
+    match (release, press) {
+        (Action::KeyUp { key }, Action::KeyDown { key: pressed, .. }) => key == pressed,
+        (
+            Action::MouseUp { button, .. },
+            Action::MouseDown {
+                button: pressed, ..
+            },
+        ) => button == pressed,
+        _ => false,
+    }
+}
+
+impl Drop for RecordQueue {
+    fn drop(&mut self) {
+        if self.inner.get_mut().unwrap().spill.take().is_some() {
+            let _ = std::fs::remove_file(&self.spill_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Key, MouseButton};
+
+    fn record(action: Action) -> EventRecord {
+        EventRecord::new(0, action)
+    }
+
+    #[test]
+    fn keeps_releases_paired_with_their_presses() {
+        let spill_path =
+            std::env::temp_dir().join(format!("microplatter-queue-{}.jsonl", std::process::id()));
+        let queue = RecordQueue::new(2, OverflowPolicy::DropOldestMoves, spill_path);
+        queue.push(record(Action::MouseDown {
+            button: MouseButton::Left,
+            x: 0,
+            y: 0,
+        }));
+        queue.push(record(Action::KeyDown {
+            key: Key::KeyA,
+            text: None,
+        }));
+        // Full with nothing to evict: the press is dropped, and later its release
+        queue.push(record(Action::KeyDown {
+            key: Key::KeyB,
+            text: None,
+        }));
+        // The release of a queued press goes over capacity
+        queue.push(record(Action::MouseUp {
+            button: MouseButton::Left,
+            x: 0,
+            y: 0,
+        }));
+        queue.push(record(Action::KeyUp { key: Key::KeyB }));
+
+        let (batch, _) = queue.take_batch(Instant::now());
+        let actions: Vec<Action> = batch.into_iter().map(|record| record.action).collect();
+        assert_eq!(
+            actions,
+            [
+                Action::MouseDown {
+                    button: MouseButton::Left,
+                    x: 0,
+                    y: 0
+                },
+                Action::KeyDown {
+                    key: Key::KeyA,
+                    text: None
+                },
+                Action::MouseUp {
+                    button: MouseButton::Left,
+                    x: 0,
+                    y: 0
+                },
+            ]
+        );
+        assert_eq!(queue.stats().dropped, 2);
+    }
+}
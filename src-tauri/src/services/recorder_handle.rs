@@ -0,0 +1,131 @@
+use crate::error::{AppError, AppResult};
+use crate::models::{AutoStopReason, QueueStats, RecordingOptions};
+use crate::services::{RecordQueue, RecordingPause, Subscription};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Controls a running recording started by `RecorderService::start_recording`.
+///
+/// `stop` ends capturing by closing the event queue and leaving the input hub; `close`
+/// additionally waits for the flusher to commit the final batch and exit.
+/// Dropping the handle stops the recording but doesn't wait for it.
+pub struct RecorderHandle {
+    session_id: i64,
+    options: RecordingOptions,
+    queue: Arc<RecordQueue>,
+    pause: Arc<Mutex<RecordingPause>>,
+    dropped: Arc<AtomicUsize>,
+    flusher: Option<JoinHandle<AppResult<()>>>,
+    subscription: Mutex<Option<Subscription>>,
+    started: Instant,
+}
+
+impl RecorderHandle {
+    pub(crate) fn new(
+        session_id: i64,
+        options: RecordingOptions,
+        queue: Arc<RecordQueue>,
+        pause: Arc<Mutex<RecordingPause>>,
+        dropped: Arc<AtomicUsize>,
+        flusher: JoinHandle<AppResult<()>>,
+        subscription: Subscription,
+    ) -> Self {
+        Self {
+            session_id,
+            options,
+            queue,
+            pause,
+            dropped,
+            flusher: Some(flusher),
+            subscription: Mutex::new(Some(subscription)),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn session_id(&self) -> i64 {
+        self.session_id
+    }
+
+    pub fn options(&self) -> &RecordingOptions {
+        &self.options
+    }
+
+    /// Mouse moves the recording filters left out so far.
+    pub fn dropped_moves(&self) -> usize {
+        self.dropped.load(Ordering::SeqCst)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pause.lock().unwrap().is_paused()
+    }
+
+    pub fn pause(&self) {
+        self.pause.lock().unwrap().pause();
+    }
+
+    pub fn resume(&self) {
+        self.pause.lock().unwrap().resume();
+        // Time spent paused doesn't count as idle
+        self.queue.touch();
+    }
+
+    /// The first auto-stop limit of the options this recording has reached, if any.
+    /// Idle time isn't counted while paused.
+    pub fn limit_reached(&self) -> Option<AutoStopReason> {
+        let (paused, paused_for) = {
+            let pause = self.pause.lock().unwrap();
+            (pause.is_paused(), pause.paused_for())
+        };
+
+        if let Some(max) = self.options.max_duration_secs {
+            let recorded = self.started.elapsed().saturating_sub(paused_for);
+            if recorded >= Duration::from_secs(max) {
+                return Some(AutoStopReason::MaxDuration);
+            }
+        }
+        if let Some(max) = self.options.max_events {
+            let stats = self.queue.stats();
+            // Events lost to overflow were never recorded
+            if stats.queued + stats.persisted >= max {
+                return Some(AutoStopReason::MaxEvents);
+            }
+        }
+        if let Some(timeout) = self.options.idle_timeout_secs {
+            if !paused && self.queue.idle_for() >= Duration::from_secs(timeout) {
+                return Some(AutoStopReason::Idle);
+            }
+        }
+        None
+    }
+
+    /// Stop capturing. The event queue closes, which lets the flusher save what is queued
+    /// and exit, and the recorder leaves the input hub.
+    pub fn stop(&self) {
+        // Close first so a callback still running in the hub queues nothing more
+        self.queue.close();
+        let subscription = self.subscription.lock().unwrap().take();
+        drop(subscription);
+    }
+
+    /// Stop capturing and block until every captured event is committed and the flusher
+    /// has exited, returning the final queue counters. Blocking, so call it off the async
+    /// runtime.
+    pub fn close(mut self) -> AppResult<QueueStats> {
+        self.stop();
+
+        if let Some(flusher) = self.flusher.take() {
+            flusher.join().unwrap_or_else(|_| {
+                Err(AppError::RecordingError("Recorder flusher panicked".to_string()))
+            })?;
+        }
+        Ok(self.queue.stats())
+    }
+}
+
+impl Drop for RecorderHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
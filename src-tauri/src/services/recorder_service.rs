@@ -1,176 +1,578 @@
-use crate::error::AppResult;
-use crate::models::{Action, EventRecord, MouseButton};
+use crate::error::{AppError, AppResult};
+use crate::models::{Action, EventRecord, Hotkey, Key, MouseButton, RecordingOptions, Redaction};
 use crate::repositories::SessionRepository;
-use crossbeam_channel::unbounded;
-use rdev::{listen, EventType};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::services::{EventSource, Journal, RecordQueue, RecorderHandle};
+use rdev::EventType;
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
-use tauri::Emitter;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{Emitter, Manager};
 use tokio::sync::Mutex as TokioMutex;
-use tokio::time::{sleep, Duration};
+
+/// How stale the cached app window geometry may get.
+const WINDOW_REFRESH: Duration = Duration::from_millis(250);
+/// Minimum gap between two updates of the recording counters sent to the frontend.
+const STATS_INTERVAL: Duration = Duration::from_millis(100);
+/// How long recorded events are collected before being saved as one batch.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+/// Failed saves in a row after which the flusher gives up at stop, leaving the unsaved
+/// batches to the journal. Also caps the retry backoff while recording.
+const SAVE_ATTEMPTS: u32 = 5;
+/// Wait before the first retry, doubled for each further failure in a row.
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Privacy mode, applied to whichever recording is running.
+static PRIVACY: AtomicBool = AtomicBool::new(false);
 
 pub struct RecorderService;
 
+/// Pause bookkeeping shared between the recording commands and the recorder callback.
+/// Time spent paused is cut out of the recorded timestamps.
+#[derive(Debug, Default)]
+pub struct RecordingPause {
+    paused_at: Option<SystemTime>,
+    paused_total: Duration,
+}
+
+impl RecordingPause {
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    pub fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(SystemTime::now());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(at) = self.paused_at.take() {
+            self.paused_total += at.elapsed().unwrap_or_default();
+        }
+    }
+
+    /// Total time spent paused, including the current pause.
+    pub fn paused_for(&self) -> Duration {
+        let current = self.paused_at.map(|at| at.elapsed().unwrap_or_default());
+        self.paused_total + current.unwrap_or_default()
+    }
+
+    /// Recording time of `time` measured from `origin`, without the paused stretches.
+    /// While paused the clock stands still at the moment the pause began.
+    fn elapsed(&self, origin: SystemTime, time: SystemTime) -> Duration {
+        let time = self.paused_at.map_or(time, |at| time.min(at));
+        time.duration_since(origin)
+            .unwrap_or_default()
+            .saturating_sub(self.paused_total)
+    }
+}
+
+/// Thins out mouse moves as configured by `RecordingOptions`, counting what it drops.
+struct MoveFilter {
+    options: RecordingOptions,
+    last_kept: Option<(i32, i32, u128)>,
+    // Latest move when only moves before clicks are kept; replaced until a click arrives
+    pending: Option<EventRecord>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl MoveFilter {
+    fn new(options: RecordingOptions, dropped: Arc<AtomicUsize>) -> Self {
+        Self {
+            options,
+            last_kept: None,
+            pending: None,
+            dropped,
+        }
+    }
+
+    /// Records that should be stored for `record`, in order.
+    fn apply(&mut self, record: EventRecord) -> Vec<EventRecord> {
+        let (x, y) = match record.action {
+            Action::MouseMove { x, y } => (x, y),
+            Action::MouseDown { .. } | Action::MouseUp { .. } | Action::Wheel { .. } => {
+                let pending = self.pending.take();
+                if let Some(EventRecord { action: Action::MouseMove { x, y }, timestamp_ms, .. }) = &pending {
+                    self.last_kept = Some((*x, *y, *timestamp_ms));
+                }
+                return pending.into_iter().chain([record]).collect();
+            }
+            _ => return vec![record],
+        };
+
+        // Compared against the last move actually stored, not one still pending
+        if let Some((last_x, last_y, last_ms)) = self.last_kept {
+            let distance = f64::from(x - last_x).hypot(f64::from(y - last_y));
+            let interval = record.timestamp_ms.saturating_sub(last_ms);
+            if distance < f64::from(self.options.min_move_distance)
+                || interval < u128::from(self.options.min_move_interval_ms)
+            {
+                self.dropped.fetch_add(1, Ordering::SeqCst);
+                return Vec::new();
+            }
+        }
+        if self.options.moves_before_clicks_only {
+            if self.pending.replace(record).is_some() {
+                self.dropped.fetch_add(1, Ordering::SeqCst);
+            }
+            return Vec::new();
+        }
+        self.last_kept = Some((x, y, record.timestamp_ms));
+        vec![record]
+    }
+}
+
+impl Drop for MoveFilter {
+    // A move still waiting for a click when recording stops is never stored
+    fn drop(&mut self) {
+        if self.pending.take().is_some() {
+            self.dropped.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Outer bounds and focus of the app's main window, so input aimed at the app itself can be
+/// left out of the recording. Queried from Tauri at most every `WINDOW_REFRESH`.
+struct AppWindow {
+    app_handle: tauri::AppHandle,
+    // left, top, right, bottom in the coordinate space rdev reports
+    bounds: Option<(i32, i32, i32, i32)>,
+    focused: bool,
+    checked_at: Option<Instant>,
+}
+
+impl AppWindow {
+    fn new(app_handle: tauri::AppHandle) -> Self {
+        Self {
+            app_handle,
+            bounds: None,
+            focused: false,
+            checked_at: None,
+        }
+    }
+
+    fn refresh(&mut self) {
+        if self.checked_at.is_some_and(|at| at.elapsed() < WINDOW_REFRESH) {
+            return;
+        }
+        self.checked_at = Some(Instant::now());
+
+        let window = self.app_handle.get_webview_window("main");
+        self.focused = window
+            .as_ref()
+            .and_then(|w| w.is_focused().ok())
+            .unwrap_or(false);
+        let shown = |w: &tauri::WebviewWindow| {
+            w.is_visible().unwrap_or(false) && !w.is_minimized().unwrap_or(false)
+        };
+        self.bounds = window.filter(shown).and_then(|w| {
+            let position = w.outer_position().ok()?;
+            let size = w.outer_size().ok()?;
+            // rdev reports logical points on macOS and physical pixels elsewhere
+            let scale = if cfg!(target_os = "macos") {
+                w.scale_factor().unwrap_or(1.0)
+            } else {
+                1.0
+            };
+            let px = |v: f64| (v / scale).round() as i32;
+            Some((
+                px(position.x as f64),
+                px(position.y as f64),
+                px(position.x as f64 + size.width as f64),
+                px(position.y as f64 + size.height as f64),
+            ))
+        });
+    }
+
+    /// Whether the event is aimed at the app: pointer events inside the window, or key
+    /// events while the window has focus. `pointer` is the last known pointer position.
+    fn targets(&mut self, event_type: &EventType, pointer: (i32, i32)) -> bool {
+        self.refresh();
+        let (x, y) = match event_type {
+            EventType::MouseMove { x, y } => (*x as i32, *y as i32),
+            EventType::KeyPress(_) | EventType::KeyRelease(_) => return self.focused,
+            _ => pointer,
+        };
+        self.bounds
+            .is_some_and(|(left, top, right, bottom)| x >= left && x < right && y >= top && y < bottom)
+    }
+}
+
+/// Keeps the app's own hotkeys out of the recording. Modifier presses are held back until
+/// the next key shows whether they start a hotkey; the keys of a hotkey are dropped along
+/// with their releases, as are releases of keys pressed before recording started.
+struct HotkeyFilter {
+    hotkeys: Vec<Hotkey>,
+    held: HashSet<Key>,
+    buffered: Vec<rdev::Event>,
+    swallowed: HashSet<Key>,
+}
+
+impl HotkeyFilter {
+    fn new(hotkeys: Vec<Hotkey>) -> Self {
+        Self {
+            hotkeys,
+            held: HashSet::new(),
+            buffered: Vec::new(),
+            swallowed: HashSet::new(),
+        }
+    }
+
+    /// Events to record for `event`, in order.
+    fn apply(&mut self, event: rdev::Event) -> Vec<rdev::Event> {
+        match event.event_type {
+            EventType::KeyPress(key) => {
+                let key = Key::from_rdev(key);
+                if self.swallowed.contains(&key) {
+                    return Vec::new();
+                }
+                if key.is_modifier() {
+                    // Auto-repeat of a modifier adds nothing to the recording
+                    if self.held.insert(key) {
+                        self.buffered.push(event);
+                    }
+                    return Vec::new();
+                }
+                if self.hotkeys.iter().any(|hotkey| hotkey.matches(key, &self.held)) {
+                    let modifiers = self.buffered.drain(..).filter_map(|e| match e.event_type {
+                        EventType::KeyPress(k) => Some(Key::from_rdev(k)),
+                        _ => None,
+                    });
+                    self.swallowed.extend(modifiers);
+                    self.swallowed.insert(key);
+                    return Vec::new();
+                }
+                self.held.insert(key);
+            }
+            EventType::KeyRelease(key) => {
+                let key = Key::from_rdev(key);
+                let was_held = self.held.remove(&key);
+                if self.swallowed.remove(&key) || !was_held {
+                    return Vec::new();
+                }
+            }
+            _ => {}
+        }
+
+        let mut events = std::mem::take(&mut self.buffered);
+        events.push(event);
+        events
+    }
+}
+
+/// Keys and buttons held down while recording.
+#[derive(Default)]
+struct InputState {
+    held: HashSet<Key>,
+    buttons: Vec<MouseButton>,
+}
+
+impl InputState {
+    /// Whether the event releases a key or button whose press was recorded, so the
+    /// release is kept even while paused and playback doesn't leave it stuck.
+    fn releases_held(&self, event_type: &EventType) -> bool {
+        match event_type {
+            EventType::KeyRelease(key) => self.held.contains(&Key::from_rdev(*key)),
+            EventType::ButtonRelease(btn) => self.buttons.contains(&MouseButton::from_rdev(btn)),
+            _ => false,
+        }
+    }
+
+    /// Whether a key press reporting `name` types text, rather than being a control key
+    /// or part of a shortcut (Ctrl/Alt/Meta held).
+    fn is_text(&self, name: &str) -> bool {
+        let held_any = |keys: &[Key]| keys.iter().any(|k| self.held.contains(k));
+        // Windows reports AltGr together with a synthetic left Ctrl
+        let shortcut = held_any(&[Key::Alt, Key::MetaLeft, Key::MetaRight])
+            || (held_any(&[Key::ControlLeft, Key::ControlRight]) && !held_any(&[Key::AltGr]));
+        !name.is_empty() && !name.chars().any(char::is_control) && !shortcut
+    }
+}
+
 impl RecorderService {
-    /// Start the recorder. This is a synchronous function designed to be called from a
-    /// plain thread (we spawn internal threads / runtimes as needed).
+    /// Turn privacy mode on or off. While on, recorded key presses are redacted as set by
+    /// `RecordingOptions::redaction`.
+    pub fn set_privacy(enabled: bool) {
+        PRIVACY.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn privacy() -> bool {
+        PRIVACY.load(Ordering::SeqCst)
+    }
+
+    /// Start recording into `session_id`: events from `source` are filtered, converted and
+    /// queued to a flusher thread that journals them in `journal_dir` and saves them in
+    /// batches. Returns immediately; the returned handle pauses, stops and finally closes
+    /// the recording.
     pub fn start_recording(
-        is_recording: Arc<Mutex<bool>>,
-        app_handle: tauri::AppHandle,
-        // pass the shared repository mutex so background async task can lock it
+        // None when running headless (no frontend to notify)
+        app_handle: Option<tauri::AppHandle>,
+        // pass the shared repository mutex so the flusher thread can lock it
         repository: Arc<TokioMutex<Box<dyn SessionRepository>>>,
         session_id: i64,
-        in_flight: Option<Arc<AtomicUsize>>,
-    ) -> AppResult<()> {
-        let start = Instant::now();
-        let last_pos = Arc::new(Mutex::new((-1, -1)));
-
-        // channel 用作生产者/消费者队列，callback 只 push 到 tx，异步任务从 rx 读取并批量保存
-        let (tx, rx) = unbounded::<EventRecord>();
-
-        // 启动一个独立的线程运行一个专用的 Tokio runtime 来定期批量写入数据库。
-        {
-            let repository = repository.clone();
-            let in_flight_bg = in_flight.clone();
-
-            std::thread::spawn(move || {
-                // 创建一个单线程的 Tokio 运行时用于异步数据库操作
-                let rt = tokio::runtime::Builder::new_current_thread()
-                    .enable_all()
-                    .build()
-                    .expect("failed to build tokio runtime for recorder flusher");
-
-                rt.block_on(async move {
-                    loop {
-                        sleep(Duration::from_millis(500)).await;
-
-                        // 批量拉取所有当前在通道里的事件
-                        let mut batch = Vec::new();
-                        while let Ok(ev) = rx.try_recv() {
-                            batch.push(ev);
-                        }
+        journal_dir: &Path,
+        source: Box<dyn EventSource>,
+        options: RecordingOptions,
+        // the app's own hotkeys, left out of the recording
+        hotkeys: Vec<Hotkey>,
+    ) -> AppResult<RecorderHandle> {
+        let origin = SystemTime::now();
+        let last_pos = Mutex::new((-1, -1));
+        let inputs = Mutex::new(InputState::default());
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let pause = Arc::new(Mutex::new(RecordingPause::default()));
+        let mut move_filter = MoveFilter::new(options.clone(), dropped.clone());
+        let mut hotkey_filter = HotkeyFilter::new(hotkeys);
+        let mut app_window = app_handle
+            .clone()
+            .filter(|_| options.exclude_app_window)
+            .map(AppWindow::new);
 
-                        if !batch.is_empty() {
-                            // lock repository inside async context
-                            let repo = repository.lock().await;
-                            if let Err(e) = repo.save_events(session_id, &batch).await {
-                                eprintln!("Failed to save events: {:?}", e);
-                            } else {
-                                if let Some(counter) = in_flight_bg.as_ref() {
-                                    counter.fetch_sub(batch.len(), Ordering::SeqCst);
-                                }
-                            }
-                        }
+        // 有界队列：callback 只 push，flusher 线程批量取出保存。
+        // 停止录制时关闭队列，flusher 写完剩余事件（包括溢出到磁盘的）后退出。
+        let spill_path = journal_dir.join(format!("spill-{}.jsonl", session_id));
+        let queue = Arc::new(RecordQueue::new(
+            options.queue_capacity,
+            options.overflow_policy,
+            spill_path,
+        ));
+
+        let journal = Journal::open(journal_dir, session_id)?;
+        let flusher = {
+            let queue = queue.clone();
+            std::thread::spawn(move || Self::run_flusher(&queue, journal, repository, session_id))
+        };
+
+        let queue_cb = queue.clone();
+        let dropped_cb = dropped.clone();
+        let pause_cb = pause.clone();
+        let redaction = options.redaction;
+        let mut stats_sent: Option<Instant> = None;
+
+        let callback = move |event: rdev::Event| {
+            // Once stopped the queue is closed and the callback does nothing
+            if queue_cb.is_closed() {
+                return;
+            }
+            // Any input counts as activity, even if it is filtered out below
+            queue_cb.touch();
+
+            for event in hotkey_filter.apply(event) {
+                // Track the pointer even through skipped moves, clicks are stamped with it
+                if let EventType::MouseMove { x, y } = event.event_type {
+                    *last_pos.lock().unwrap() = (x as i32, y as i32);
+                }
+
+                let elapsed = {
+                    let pause = pause_cb.lock().unwrap();
+                    let pointer = *last_pos.lock().unwrap();
+                    let skip = pause.is_paused()
+                        || app_window
+                            .as_mut()
+                            .is_some_and(|window| window.targets(&event.event_type, pointer));
+                    // Releases of recorded presses are always kept so nothing stays held on playback
+                    if skip && !inputs.lock().unwrap().releases_held(&event.event_type) {
+                        continue;
                     }
-                });
-            });
-        }
+                    pause.elapsed(origin, event.time).as_millis()
+                };
 
-        let is_recording_cb = is_recording.clone();
-        let tx_cb = tx.clone();
-        let last_pos_cb = last_pos.clone();
-        let in_flight_thread = in_flight.clone();
+                let redact = Self::privacy().then_some(redaction);
+                if let Some(action) = Self::to_action(&event, &last_pos, &inputs, redact) {
+                    for record in move_filter.apply(EventRecord::new(elapsed, action)) {
+                        queue_cb.push(record);
+                    }
+                }
+            }
 
-        std::thread::spawn(move || {
-            let callback = move |event: rdev::Event| {
-                let is_rec = *is_recording_cb.lock().unwrap();
-                if !is_rec {
+            // 发当前未保存计数给前端，最多每 STATS_INTERVAL 一次
+            if let Some(app_handle) = app_handle.as_ref() {
+                if stats_sent.is_some_and(|at| at.elapsed() < STATS_INTERVAL) {
                     return;
                 }
+                stats_sent = Some(Instant::now());
+                let stats = queue_cb.stats();
+                let _ = app_handle.emit("event-count", stats.queued);
+                let _ = app_handle.emit("recording-queue", stats);
+                let _ = app_handle.emit("moves-dropped", dropped_cb.load(Ordering::SeqCst));
+            }
+        };
 
-                let elapsed = start.elapsed().as_millis();
-
-                // 先读取位置信息（避免双锁交叉）
-                let (x, y) = *last_pos_cb.lock().unwrap();
-
-                match event.event_type {
-                    EventType::MouseMove { x, y } => {
-                        *last_pos_cb.lock().unwrap() = (x as i32, y as i32);
-                        let ev = EventRecord::new(
-                            elapsed,
-                            Action::MouseMove {
-                                x: x as i32,
-                                y: y as i32,
-                            },
-                        );
-                        if tx_cb.send(ev).is_ok() {
-                            if let Some(counter) = in_flight_thread.as_ref() {
-                                counter.fetch_add(1, Ordering::SeqCst);
-                            }
-                        }
-                    }
+        let subscription = match source.listen(origin, Box::new(callback)) {
+            Ok(subscription) => subscription,
+            Err(e) => {
+                // Close the queue so the flusher exits
+                queue.close();
+                let _ = flusher.join();
+                return Err(e);
+            }
+        };
 
-                    EventType::ButtonPress(btn) => {
-                        let button = MouseButton::from_rdev(&btn);
+        Ok(RecorderHandle::new(
+            session_id,
+            options,
+            queue,
+            pause,
+            dropped,
+            flusher,
+            subscription,
+        ))
+    }
 
-                        let ev = EventRecord::new(elapsed, Action::MouseDown { button, x, y });
-                        if tx_cb.send(ev).is_ok() {
-                            if let Some(counter) = in_flight_thread.as_ref() {
-                                counter.fetch_add(1, Ordering::SeqCst);
-                            }
-                        }
-                    }
+    /// Save queued events every `FLUSH_INTERVAL` until the queue closes, then save what is
+    /// left and return. Each batch is journaled as soon as it is taken. Failed saves are
+    /// retried on later rounds with backoff, oldest batch first, so new batches keep being
+    /// journaled while the repository is down. After the queue closes the flusher gives up
+    /// once `SAVE_ATTEMPTS` saves in a row failed, leaving the rest to be replayed from the
+    /// journal on the next start, and returns the last save error.
+    fn run_flusher(
+        queue: &RecordQueue,
+        mut journal: Journal,
+        repository: Arc<TokioMutex<Box<dyn SessionRepository>>>,
+        session_id: i64,
+    ) -> AppResult<()> {
+        // 创建一个单线程的 Tokio 运行时用于异步数据库操作
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| AppError::RecordingError(format!("Failed to start flusher: {}", e)))?;
 
-                    EventType::ButtonRelease(btn) => {
-                        let button = MouseButton::from_rdev(&btn);
+        // Batches taken but not saved yet, oldest first, with their journal sequence number
+        let mut unsaved: VecDeque<(Option<u64>, Vec<EventRecord>)> = VecDeque::new();
+        let mut failures = 0;
+        let mut retry_at = Instant::now();
+        loop {
+            let mut deadline = Instant::now() + FLUSH_INTERVAL;
+            if !unsaved.is_empty() {
+                deadline = deadline.min(retry_at);
+            }
+            let (batch, done) = queue.take_batch(deadline);
 
-                        let ev = EventRecord::new(elapsed, Action::MouseUp { button, x, y });
-                        if tx_cb.send(ev).is_ok() {
-                            if let Some(counter) = in_flight_thread.as_ref() {
-                                counter.fetch_add(1, Ordering::SeqCst);
-                            }
-                        }
-                    }
+            if !batch.is_empty() {
+                let seq = journal
+                    .append(&batch)
+                    .inspect_err(|e| eprintln!("Failed to journal {} events: {}", batch.len(), e))
+                    .ok();
+                unsaved.push_back((seq, batch));
+            }
 
-                    EventType::Wheel { delta_x, delta_y } => {
-                        let ev = EventRecord::new(
-                            elapsed,
-                            Action::Wheel {
-                                delta_x: delta_x as i32,
-                                delta_y: delta_y as i32,
-                                x,
-                                y,
-                            },
-                        );
-                        if tx_cb.send(ev).is_ok() {
-                            if let Some(counter) = in_flight_thread.as_ref() {
-                                counter.fetch_add(1, Ordering::SeqCst);
-                            }
-                        }
+            let mut error = None;
+            if Instant::now() >= retry_at {
+                while let Some((seq, batch)) = unsaved.front() {
+                    let (seq, count) = (*seq, batch.len());
+                    let saved = rt.block_on(async {
+                        let repo = repository.lock().await;
+                        repo.save_events(session_id, batch).await
+                    });
+                    if let Err(e) = saved {
+                        failures += 1;
+                        eprintln!("Saving {} events failed (attempt {}): {}", count, failures, e);
+                        retry_at = Instant::now() + RETRY_DELAY * 2u32.pow(failures.min(SAVE_ATTEMPTS) - 1);
+                        error = Some(e);
+                        break;
                     }
 
-                    EventType::KeyPress(key) => {
-                        let ev = EventRecord::new(
-                            elapsed,
-                            Action::KeyPress {
-                                key: format!("{:?}", key),
-                            },
-                        );
-                        if tx_cb.send(ev).is_ok() {
-                            if let Some(counter) = in_flight_thread.as_ref() {
-                                counter.fetch_add(1, Ordering::SeqCst);
-                            }
+                    failures = 0;
+                    unsaved.pop_front();
+                    queue.mark_persisted(count);
+                    if let Some(seq) = seq {
+                        if let Err(e) = journal.commit(seq) {
+                            eprintln!("Failed to commit journal batch {}: {}", seq, e);
                         }
                     }
+                }
+            }
 
-                    _ => {}
+            if done {
+                if unsaved.is_empty() {
+                    journal.finish();
+                    return Ok(());
                 }
+                if let Some(e) = error.filter(|_| failures >= SAVE_ATTEMPTS) {
+                    let count: usize = unsaved.iter().map(|(_, batch)| batch.len()).sum();
+                    eprintln!("Failed to save {} events, kept in journal: {:?}", count, e);
+                    journal.finish();
+                    return Err(e);
+                }
+                // Nothing is left to take, so just wait for the next attempt
+                std::thread::sleep(retry_at.saturating_duration_since(Instant::now()));
+            }
+        }
+    }
 
-                // 发当前未保存计数给前端
-                let count = in_flight_thread
-                    .as_ref()
-                    .map(|c| c.load(Ordering::SeqCst))
-                    .unwrap_or(0usize);
-                let _ = app_handle.emit("event-count", count);
-            };
+    /// Translate a raw input event into a recorded action. Button and wheel events are
+    /// stamped with the last pointer position. Key presses that type characters keep
+    /// them as the press's `text`. With `redact` set, key presses are
+    /// redacted and their releases dropped.
+    fn to_action(
+        event: &rdev::Event,
+        last_pos: &Mutex<(i32, i32)>,
+        inputs: &Mutex<InputState>,
+        redact: Option<Redaction>,
+    ) -> Option<Action> {
+        let (x, y) = *last_pos.lock().unwrap();
 
-            if let Err(err) = listen(callback) {
-                eprintln!("Listening failed: {:?}", err);
+        match &event.event_type {
+            EventType::MouseMove { x, y } => Some(Action::MouseMove {
+                x: *x as i32,
+                y: *y as i32,
+            }),
+            EventType::ButtonPress(btn) => {
+                let button = MouseButton::from_rdev(btn);
+                inputs.lock().unwrap().buttons.push(button.clone());
+                Some(Action::MouseDown { button, x, y })
             }
-        });
+            EventType::ButtonRelease(btn) => {
+                // A release whose press wasn't recorded (before start, or on the app window) is dropped
+                let button = MouseButton::from_rdev(btn);
+                let mut inputs = inputs.lock().unwrap();
+                let pos = inputs.buttons.iter().position(|b| *b == button)?;
+                inputs.buttons.remove(pos);
+                Some(Action::MouseUp { button, x, y })
+            }
+            EventType::Wheel { delta_x, delta_y } => Some(Action::Wheel {
+                delta_x: *delta_x as i32,
+                delta_y: *delta_y as i32,
+                x,
+                y,
+            }),
+            EventType::KeyPress(_) if redact.is_some() => {
+                // Not tracked as held, so the release is dropped as well
+                (redact == Some(Redaction::Placeholder)).then_some(Action::Redacted)
+            }
+            EventType::KeyPress(key) => {
+                let key = Key::from_rdev(*key);
+                let mut inputs = inputs.lock().unwrap();
+                let text = event.name.clone().filter(|name| inputs.is_text(name));
+                inputs.held.insert(key);
+                Some(Action::KeyDown { key, text })
+            }
+            EventType::KeyRelease(key) => {
+                // A release whose press wasn't recorded is dropped
+                let key = Key::from_rdev(*key);
+                inputs.lock().unwrap().held.remove(&key).then_some(Action::KeyUp { key })
+            }
+        }
+    }
 
-        Ok(())
+    /// Drop the mouse moves and scrolling recorded after the session's last click, such as the
+    /// pointer travelling back to the app to stop recording. Keys typed after it are kept.
+    /// Returns how many events were removed.
+    pub async fn trim_after_last_click(
+        session_id: i64,
+        repository: &dyn SessionRepository,
+    ) -> AppResult<usize> {
+        let events = repository.load_events(session_id).await?;
+        let last_click = events
+            .iter()
+            .rev()
+            .find(|r| matches!(r.action, Action::MouseDown { .. } | Action::MouseUp { .. }));
+        match last_click {
+            Some(click) => repository.delete_pointer_events_after(session_id, click.timestamp_ms).await,
+            None => Ok(0),
+        }
     }
 
     /// Save recording helper used by the command module when stopping.
@@ -179,9 +581,191 @@ impl RecorderService {
         session_id: i64,
         repository: &dyn SessionRepository,
     ) -> AppResult<usize> {
-        // After recording, the flusher should already have persisted events.
+        // Once the recorder is closed every event has been persisted.
         // To report how many events were stored, load them and return the count.
         let events = repository.load_events(session_id).await?;
         Ok(events.len())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SessionStatus;
+    use crate::repositories::SqliteSessionRepository;
+    use crate::services::ScriptedEventSource;
+
+    fn moved(timestamp_ms: u128, x: i32, y: i32) -> EventRecord {
+        EventRecord::new(timestamp_ms, Action::MouseMove { x, y })
+    }
+
+    fn clicked(timestamp_ms: u128, x: i32, y: i32) -> EventRecord {
+        EventRecord::new(timestamp_ms, Action::MouseDown { button: MouseButton::Left, x, y })
+    }
+
+    #[test]
+    fn move_filter_thins_moves_against_stored_ones() {
+        let options = RecordingOptions {
+            min_move_distance: 5,
+            min_move_interval_ms: 20,
+            ..RecordingOptions::default()
+        };
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut filter = MoveFilter::new(options, dropped.clone());
+
+        let kept: Vec<EventRecord> = [
+            moved(0, 0, 0),
+            moved(10, 10, 0),  // too soon
+            moved(30, 2, 0),   // too close
+            moved(40, 10, 0),
+            clicked(41, 10, 0),
+            moved(45, 20, 0),  // too soon, clicks don't count
+        ]
+        .into_iter()
+        .flat_map(|record| filter.apply(record))
+        .collect();
+        assert_eq!(kept, [moved(0, 0, 0), moved(40, 10, 0), clicked(41, 10, 0)]);
+        assert_eq!(dropped.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn move_filter_keeps_only_the_move_before_each_click() {
+        let options = RecordingOptions {
+            min_move_distance: 5,
+            moves_before_clicks_only: true,
+            ..RecordingOptions::default()
+        };
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut filter = MoveFilter::new(options, dropped.clone());
+
+        let kept: Vec<EventRecord> = [
+            moved(0, 0, 0),
+            // Nothing is stored yet to be too close to, so this replaces the first
+            moved(10, 3, 0),
+            clicked(20, 3, 0),
+            moved(30, 6, 0), // too close to the stored (3, 0)
+            moved(40, 110, 0),
+            clicked(50, 110, 0),
+            moved(60, 200, 0),
+        ]
+        .into_iter()
+        .flat_map(|record| filter.apply(record))
+        .collect();
+        assert_eq!(
+            kept,
+            [moved(10, 3, 0), clicked(20, 3, 0), moved(40, 110, 0), clicked(50, 110, 0)]
+        );
+        assert_eq!(dropped.load(Ordering::SeqCst), 2);
+
+        // The trailing move never gets its click
+        drop(filter);
+        assert_eq!(dropped.load(Ordering::SeqCst), 3);
+    }
+
+    fn hotkey_filtered(filter: &mut HotkeyFilter, events: &[EventType]) -> Vec<EventType> {
+        events
+            .iter()
+            .flat_map(|event_type| {
+                filter.apply(rdev::Event {
+                    time: SystemTime::now(),
+                    name: None,
+                    event_type: *event_type,
+                })
+            })
+            .map(|event| event.event_type)
+            .collect()
+    }
+
+    #[test]
+    fn hotkey_filter_swallows_only_hotkeys() {
+        use rdev::Key::{ControlLeft, KeyA, KeyR, ShiftLeft};
+        use EventType::{KeyPress, KeyRelease};
+        let mut filter = HotkeyFilter::new(vec![Hotkey::parse("Ctrl+Shift+R").unwrap()]);
+
+        // The hotkey and the modifiers held for it never reach the recording
+        let swallowed = [
+            KeyPress(ControlLeft),
+            KeyPress(ShiftLeft),
+            KeyPress(KeyR),
+            KeyRelease(KeyR),
+            KeyRelease(ShiftLeft),
+            KeyRelease(ControlLeft),
+        ];
+        assert_eq!(hotkey_filtered(&mut filter, &swallowed), []);
+
+        // Buffered modifiers come out, once, with the next key that isn't a hotkey
+        let shortcut = [
+            KeyPress(ControlLeft),
+            KeyPress(ControlLeft),
+            KeyPress(KeyA),
+            KeyRelease(KeyA),
+            KeyRelease(ControlLeft),
+        ];
+        assert_eq!(hotkey_filtered(&mut filter, &shortcut), shortcut[1..]);
+
+        // A release whose press came before recording started is dropped
+        let typed = [KeyRelease(ShiftLeft), KeyPress(KeyR), KeyRelease(KeyR)];
+        assert_eq!(hotkey_filtered(&mut filter, &typed), typed[1..]);
+    }
+
+    #[tokio::test]
+    async fn records_scripted_events_into_repository() {
+        let repository: Box<dyn SessionRepository> =
+            Box::new(SqliteSessionRepository::new(":memory:".to_string()).unwrap());
+        repository.init().await.unwrap();
+        let session_id = repository
+            .create_session("scripted", None, SessionStatus::Recording)
+            .await
+            .unwrap();
+        let repository = Arc::new(TokioMutex::new(repository));
+        let journal_dir = std::env::temp_dir().join(format!("microplatter-test-{}", std::process::id()));
+
+        let source = ScriptedEventSource::new()
+            .event(0, EventType::MouseMove { x: 100.0, y: 200.0 })
+            .event(40, EventType::ButtonPress(rdev::Button::Left))
+            .event(90, EventType::ButtonRelease(rdev::Button::Left))
+            .event(150, EventType::Wheel { delta_x: 0, delta_y: -1 })
+            .typed(300, EventType::KeyPress(rdev::Key::KeyA), "a")
+            .event(380, EventType::KeyRelease(rdev::Key::KeyA))
+            .event(400, EventType::KeyPress(rdev::Key::Escape))
+            .event(450, EventType::KeyRelease(rdev::Key::Escape));
+        let recorder = RecorderService::start_recording(
+            None,
+            repository.clone(),
+            session_id,
+            &journal_dir,
+            Box::new(source),
+            RecordingOptions::default(),
+            Vec::new(),
+        )
+        .unwrap();
+        let stats = recorder.close().unwrap();
+        assert_eq!((stats.queued, stats.persisted, stats.dropped), (0, 8, 0));
+
+        let stored: Vec<(u128, Action)> = repository
+            .lock()
+            .await
+            .load_events(session_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|record| (record.timestamp_ms, record.action))
+            .collect();
+        let left = MouseButton::Left;
+        assert_eq!(
+            stored,
+            [
+                (0, Action::MouseMove { x: 100, y: 200 }),
+                (40, Action::MouseDown { button: left.clone(), x: 100, y: 200 }),
+                (90, Action::MouseUp { button: left, x: 100, y: 200 }),
+                (150, Action::Wheel { delta_x: 0, delta_y: -1, x: 100, y: 200 }),
+                (300, Action::KeyDown { key: Key::KeyA, text: Some("a".to_string()) }),
+                (380, Action::KeyUp { key: Key::KeyA }),
+                (400, Action::KeyDown { key: Key::Escape, text: None }),
+                (450, Action::KeyUp { key: Key::Escape }),
+            ]
+        );
+        assert!(!journal_dir.join(format!("session-{}.jsonl", session_id)).exists());
+        std::fs::remove_dir_all(&journal_dir).unwrap();
+    }
+}
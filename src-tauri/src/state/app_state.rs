@@ -3,31 +3,69 @@ use crate::repositories::{SessionRepository, SqliteSessionRepository};
 #[cfg(feature = "postgres")]
 use crate::repositories::PostgresSessionRepository;
 use crate::error::AppResult;
+use crate::models::{RecordingOptions, SessionStatus};
+use crate::services::{Journal, PlaybackControl, RecorderHandle};
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex as StdMutex};
 use tokio::sync::Mutex as TokioMutex;
-use std::sync::atomic::AtomicUsize;
 
 pub struct AppState {
+    pub config: AppConfig,
     pub is_recording: Arc<StdMutex<bool>>,
     pub current_session_id: Arc<StdMutex<Option<i64>>>,
     pub repository: Arc<TokioMutex<Box<dyn SessionRepository>>>,
-    // Optional counter exposed by recorder to indicate number of in-flight events
-    pub recorder_in_flight: Arc<TokioMutex<Option<Arc<AtomicUsize>>>>,
+    // Handle of the running recorder, taken when the recording is stopped
+    pub recorder: StdMutex<Option<RecorderHandle>>,
+    // Cancel flag of a countdown before recording starts
+    pub countdown: StdMutex<Option<Arc<AtomicBool>>>,
+    // Options of the last recording started from the UI, reused by the record hotkey
+    pub recording_options: StdMutex<RecordingOptions>,
+    // Most recent playback; kept after it ends so its final status can still be queried
+    pub playback: Arc<StdMutex<Option<Arc<PlaybackControl>>>>,
+    // A playback is loading and not in `playback` yet; set and cleared under the `playback` lock
+    pub playback_starting: AtomicBool,
 }
 
 impl AppState {
     pub async fn new(app_handle: &tauri::AppHandle) -> AppResult<Self> {
         let config = AppConfig::load_from_env(app_handle);
-        let repository = Self::create_repository(config.database).await?;
+        let repository = Self::create_repository(config.database.clone()).await?;
+
+        // 回放上次未写入数据库的事件（崩溃或数据库故障）
+        match Journal::replay(&config.journal_dir, &*repository).await {
+            Ok(0) => {}
+            Ok(count) => println!("Recovered {} journaled events", count),
+            Err(e) => eprintln!("Failed to replay event journal: {}", e),
+        }
+        Self::recover_sessions(&*repository).await?;
         
         Ok(Self {
+            config,
             is_recording: Arc::new(StdMutex::new(false)),
             current_session_id: Arc::new(StdMutex::new(None)),
             repository: Arc::new(TokioMutex::new(repository)),
-            recorder_in_flight: Arc::new(TokioMutex::new(None)),
+            recorder: StdMutex::new(None),
+            countdown: StdMutex::new(None),
+            recording_options: StdMutex::new(RecordingOptions::default()),
+            playback: Arc::new(StdMutex::new(None)),
+            playback_starting: AtomicBool::new(false),
         })
     }
     
+    /// Mark sessions still "recording" from a previous run as aborted. Their saved events,
+    /// including any just replayed from the journal, stay playable.
+    async fn recover_sessions(repository: &dyn SessionRepository) -> AppResult<()> {
+        let sessions = repository.list_sessions().await?;
+        for session in sessions.iter().filter(|s| s.status == SessionStatus::Recording) {
+            repository.set_session_status(session.id, SessionStatus::Aborted).await?;
+            println!(
+                "Marked interrupted session {} as aborted ({} events recovered)",
+                session.id, session.event_count
+            );
+        }
+        Ok(())
+    }
+    
     async fn create_repository(
         config: DatabaseConfig,
     ) -> AppResult<Box<dyn SessionRepository>> {